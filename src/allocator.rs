@@ -0,0 +1,69 @@
+use core::alloc::{AllocError, Allocator, Layout};
+use core::ptr::NonNull;
+use crate::BumpAllocRef;
+
+/// Lets std collections allocate from the arena, as in `Vec::new_in(&alloc_ref)`
+///
+/// Each allocation is frozen on its own, so the top vector must be empty, which the shared borrow keeps it while the
+/// collections are alive. Freeing only gives the memory back if it's the most recent allocation, and growing happens
+/// in place for the most recent one; other blocks are copied to a new allocation.
+unsafe impl<'data> Allocator for &BumpAllocRef<'data> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let alloc = unsafe { &mut *self.ptr };
+        if alloc.top_size != 0 {
+            return Err(AllocError);
+        }
+        let padding = alloc.top_base.align_offset(layout.align());
+        alloc.try_ensure(padding.saturating_add(layout.size())).map_err(|_| AllocError)?;
+        alloc.top_base = unsafe { alloc.top_base.add(padding) };
+        alloc.top_size = layout.size();
+        let start = alloc.freeze_top();
+        alloc.unstamp_last();
+        Ok(NonNull::slice_from_raw_parts(unsafe { NonNull::new_unchecked(start) }, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let alloc = unsafe { &mut *self.ptr };
+        if alloc.top_size == 0 && !alloc.canaries && unsafe { ptr.as_ptr().add(layout.size()) } == alloc.top_base {
+            alloc.top_base = ptr.as_ptr();
+            alloc.top_size = layout.size();
+            alloc.shrink_top(0);
+            alloc.forget_records(alloc.top_base);
+        }
+    }
+
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let alloc = unsafe { &mut *self.ptr };
+        let last = alloc.top_size == 0 && !alloc.canaries && unsafe { ptr.as_ptr().add(old.size()) } == alloc.top_base;
+        if last && ptr.as_ptr().cast::<u8>().align_offset(new.align()) == 0 {
+            alloc.try_ensure(new.size() - old.size()).map_err(|_| AllocError)?;
+            alloc.top_base = unsafe { ptr.as_ptr().add(new.size()) };
+            #[cfg(feature = "std")]
+            if let Some(record) = alloc.registry.as_mut().and_then(|registry| registry.last_mut()) {
+                record.len = new.size();
+            }
+            return Ok(NonNull::slice_from_raw_parts(ptr, new.size()));
+        }
+        let block = self.allocate(new)?;
+        unsafe { core::ptr::copy_nonoverlapping(ptr.as_ptr(), block.as_ptr().cast::<u8>(), old.size()) };
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BumpAlloc;
+
+    #[test]
+    fn collections() {
+        let mut alloc = BumpAlloc::new();
+        let alloc_ref = alloc.to_ref();
+        let mut v: Vec<u32, _> = Vec::new_in(&alloc_ref);
+        v.extend(0..1000);
+        let b = Box::new_in(7u64, &alloc_ref);
+        v.push(*b as u32);
+        assert_eq!((v.len(), v[999], v[1000]), (1001, 999, 7));
+        assert!(alloc_ref.contains(v.as_ptr().cast()));
+        assert_eq!(&*b as *const u64 as usize % 8, 0);
+    }
+}
@@ -0,0 +1,1993 @@
+use core::marker::PhantomData;
+use crate::{LiquidVecRef, PageSource, FixedBuffer, Pod};
+#[cfg(feature = "std")]
+use crate::{OsPages, GuardedPages, sys};
+
+/// Reserving the address space of a `BumpAlloc` failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError {
+    size: usize,
+    errno: i32,
+}
+
+impl AllocError {
+    /// The number of bytes of address space that was requested
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The OS error code reported by the failed call (`GetLastError` on Windows), 0 if the OS didn't report one
+    pub fn errno(&self) -> i32 {
+        self.errno
+    }
+}
+
+impl core::fmt::Display for AllocError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.errno == 0 {
+            write!(f, "failed to reserve {} bytes of address space", self.size)
+        } else {
+            write!(f, "failed to reserve {} bytes of address space: {}", self.size, OsError(self.errno))
+        }
+    }
+}
+
+impl core::error::Error for AllocError {}
+
+/// The top vector can't grow by the requested number of bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError {
+    requested: usize,
+    remaining: usize,
+    errno: i32,
+}
+
+impl CapacityError {
+    /// The number of bytes the top vector had to grow by
+    pub fn requested(&self) -> usize {
+        self.requested
+    }
+
+    /// The number of bytes of address space left in the arena
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// The OS error code if the memory was there but couldn't be committed, 0 if the arena is exhausted
+    pub fn errno(&self) -> i32 {
+        self.errno
+    }
+}
+
+impl core::fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.errno == 0 {
+            write!(f, "BumpAlloc exhausted: {} more bytes requested with {} bytes left", self.requested, self.remaining)
+        } else {
+            write!(f, "failed to commit {} more bytes of BumpAlloc memory: {}", self.requested, OsError(self.errno))
+        }
+    }
+}
+
+impl core::error::Error for CapacityError {}
+
+/// An `ArenaSpan` doesn't point at frozen bytes of the arena it's resolved in
+///
+/// Either it's from before a `reset` of the arena, or it goes past the bytes frozen so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleHandle {
+    span: ArenaSpan,
+    generation: u32,
+}
+
+impl StaleHandle {
+    /// The span that couldn't be resolved
+    pub fn span(&self) -> ArenaSpan {
+        self.span
+    }
+
+    /// The generation of the arena at the time, see `BumpAlloc::generation`
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl core::fmt::Display for StaleHandle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.span.generation != self.generation {
+            write!(f, "stale ArenaSpan: made in generation {} of an arena now in generation {}",
+                   self.span.generation, self.generation)
+        } else {
+            write!(f, "ArenaSpan of {} bytes at offset {} goes past the frozen bytes of the arena",
+                   self.span.len, self.span.offset)
+        }
+    }
+}
+
+impl core::error::Error for StaleHandle {}
+
+/// A frozen allocation no longer matches the checksum taken when it was frozen, see `BumpAlloc::verify_all`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    span: ArenaSpan,
+    expected: u32,
+    found: u32,
+}
+
+impl ChecksumMismatch {
+    /// The allocation that changed
+    pub fn span(&self) -> ArenaSpan {
+        self.span
+    }
+
+    /// The CRC32 of the allocation when it was frozen
+    pub fn expected(&self) -> u32 {
+        self.expected
+    }
+
+    /// The CRC32 of the allocation now
+    pub fn found(&self) -> u32 {
+        self.found
+    }
+}
+
+impl core::fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "the allocation of {} bytes at offset {} changed since it was frozen: CRC32 {:08x} instead of {:08x}",
+               self.span.len, self.span.offset, self.found, self.expected)
+    }
+}
+
+impl core::error::Error for ChecksumMismatch {}
+
+/// Displays an OS error code, as the OS describes it if the OS layer is there
+struct OsError(i32);
+
+impl core::fmt::Display for OsError {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        std::io::Error::from_raw_os_error(self.0).fmt(f)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "os error {}", self.0)
+    }
+}
+
+/// The `PageSource` used when none is named, `OsPages` when the OS layer is there
+#[cfg(feature = "std")]
+pub type DefaultPages = OsPages;
+
+/// The `PageSource` used when none is named, `FixedBuffer` when there's no OS layer
+#[cfg(not(feature = "std"))]
+pub type DefaultPages = FixedBuffer;
+
+/// Memory is committed in steps of at least this many bytes, to keep the commit calls off the write path
+pub(crate) const COMMIT_GRANULARITY: usize = 1 << 16;
+
+/// An arena of `2^bits` bytes of address space, handing out frozen slices front to back
+///
+/// The memory comes from a `PageSource`, which defaults to anonymous pages from the OS.
+pub struct BumpAlloc<S: ?Sized + PageSource = DefaultPages> {
+    pub(crate) address_space: usize,
+    pub(crate) data_base: *mut u8,
+    pub(crate) top_base: *mut u8,
+    pub(crate) top_size: usize,
+    pub(crate) committed: usize,
+    pub(crate) overflow: OverflowPolicy,
+    pub(crate) canaries: bool,
+    pub(crate) last_canary: *mut u8,
+    pub(crate) drop_policy: DropPolicy,
+    pub(crate) drop_hook: Option<fn(usize)>,
+    pub(crate) generation: u32,
+    pub(crate) freezes: usize,
+    pub(crate) hooks: Hooks,
+    pub(crate) high_water: usize,
+    pub(crate) dirty: usize,
+    /// End of the room last asked for with `LiquidVecRef::reserve`, which `spare_capacity_mut` hands out at least
+    pub(crate) reserved: usize,
+    #[cfg(feature = "std")]
+    pub(crate) registry: Option<Vec<Record>>,
+    /// Whether registry records get a checksum of the allocation
+    #[cfg(feature = "std")]
+    pub(crate) checksums: bool,
+    /// Offset and length of the slices frozen with `freeze_dedup`, by hash of their contents
+    #[cfg(feature = "std")]
+    pub(crate) dedup: Option<std::collections::HashMap<u64, Vec<(usize, usize)>>>,
+    pub(crate) drop_list: *mut DropNode,
+    pub(crate) source: S,
+}
+
+/// What a `BumpAlloc` does when the top vector has to grow past the end of the arena
+///
+/// The infallible growth methods of `LiquidVecRef` panic whenever growing fails, the `try_` ones return the error
+/// unless the policy is `Panic`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Panic on every growth path, `try_` methods included
+    Panic,
+    /// Report a `CapacityError` from the `try_` methods
+    #[default]
+    Error,
+    /// Try to extend the reservation in place first, see `PageSource::grow`, then report an error like `Error`
+    Grow,
+}
+
+/// Where a frozen allocation lies in its arena, as a byte offset from the start and a length in bytes
+///
+/// Unlike a slice it's plain data, so it stays meaningful when written to disk or sent to another process that maps
+/// the same arena contents. `BumpAlloc::resolve` turns it back into bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArenaSpan {
+    offset: usize,
+    len: usize,
+    generation: u32,
+}
+
+impl ArenaSpan {
+    /// The span of `len` bytes starting `offset` bytes into the arena
+    pub fn new(offset: usize, len: usize) -> Self {
+        ArenaSpan { offset, len, generation: 0 }
+    }
+
+    /// The same span, in the given generation of the arena
+    pub fn with_generation(self, generation: u32) -> Self {
+        ArenaSpan { generation, ..self }
+    }
+
+    /// Start of the allocation, in bytes from the start of the arena
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Size of the allocation in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The generation of the arena the span was frozen in, see `BumpAlloc::generation`
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// Where a frozen allocation lies in the arena, kept by allocators with a registry
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Record {
+    pub(crate) offset: usize,
+    pub(crate) len: usize,
+    pub(crate) tag: Option<u32>,
+    /// CRC32 of the bytes when they were frozen, if the allocator takes checksums
+    pub(crate) crc: Option<u32>,
+}
+
+/// The destructor of a value put in the arena by `BumpAllocRef::alloc_with_drop`, stored right before the value
+///
+/// The nodes form a list from the most recent one back, which is also from the highest address down.
+pub(crate) struct DropNode {
+    prev: *mut DropNode,
+    value: *mut u8,
+    drop: unsafe fn(*mut u8),
+}
+
+/// A snapshot of how much of a `BumpAlloc` is in use, see `BumpAllocRef::stats`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Bytes allocated so far, the top vector included
+    pub allocated: usize,
+    /// Bytes in frozen allocations, with the padding and canaries between them
+    pub frozen: usize,
+    /// Number of freezes since the allocator was created
+    pub freezes: usize,
+    /// The most bytes that were allocated at any one time, across resets and rollbacks
+    pub high_water: usize,
+    /// Size of the reserved address space
+    pub address_space: usize,
+    /// Bytes of the used part of the arena that are backed by physical memory, if the page source can tell
+    pub resident: Option<usize>,
+}
+
+/// Callbacks a `BumpAlloc` makes as it's used, see `BumpAlloc::with_hooks`
+///
+/// They're plain function pointers so they work without `std`; state like a quota goes in statics.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Hooks {
+    /// Called with the span of every allocation as it's frozen, not counting canaries
+    pub on_freeze: Option<fn(ArenaSpan)>,
+    /// Called with the offset and size of every range of memory the arena takes from its source beyond
+    /// what it started with: newly committed pages, or address space it grew into that comes committed
+    pub on_reserve: Option<fn(usize, usize)>,
+    /// Called with the number of bytes that were in use on every `reset`
+    pub on_reset: Option<fn(usize)>,
+}
+
+/// What a `BumpAlloc` does with the top vector's bytes when its `LiquidVecRef` is dropped without being frozen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Leave the bytes in place, the next `top()` continues the same vector
+    #[default]
+    Keep,
+    /// Throw the bytes away and give their space back to the arena
+    Rollback,
+    /// Freeze the bytes as if `freeze()` had been called, leaking the slice
+    Freeze,
+}
+
+#[cfg(feature = "std")]
+impl Default for BumpAlloc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl BumpAlloc {
+    /// New Bump allocator with at most ~4GB of stuff in it (256MB on 32-bit targets, 16MB on wasm)
+    pub fn new() -> Self {
+        Self::new_with_address_space(sys::DEFAULT_BITS)
+    }
+
+    /// New Bump allocator with at most ~2^bits stuff in it
+    pub fn new_with_address_space(bits: u8) -> Self {
+        Self::try_new_with_address_space(bits).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but returns an error instead of panicking when the address space can't be reserved
+    pub fn try_new() -> Result<Self, AllocError> {
+        Self::try_new_with_address_space(sys::DEFAULT_BITS)
+    }
+
+    /// Like `new_with_address_space`, but returns an error instead of panicking when the address space can't be reserved
+    pub fn try_new_with_address_space(bits: u8) -> Result<Self, AllocError> {
+        let Some(size) = 1usize.checked_shl(bits as u32) else {
+            return Err(AllocError { size: usize::MAX, errno: sys::EINVAL })
+        };
+        Self::try_new_in(OsPages, size)
+    }
+}
+
+#[cfg(feature = "std")]
+impl BumpAlloc<GuardedPages> {
+    /// Like `new_with_address_space`, followed by a guard page that faults when anything runs off the end of the arena
+    pub fn new_with_guard_page(bits: u8) -> Self {
+        Self::try_new_with_guard_page(bits).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new_with_guard_page`, but returns an error instead of panicking when the address space can't be reserved
+    pub fn try_new_with_guard_page(bits: u8) -> Result<Self, AllocError> {
+        let Some(size) = 1usize.checked_shl(bits as u32) else {
+            return Err(AllocError { size: usize::MAX, errno: sys::EINVAL })
+        };
+        Self::try_new_in(GuardedPages, size)
+    }
+}
+
+impl BumpAlloc<FixedBuffer> {
+    /// New Bump allocator over a preallocated buffer instead of memory from the OS
+    ///
+    /// Running out of buffer panics like running out of address space does.
+    pub fn from_buffer(buffer: &'static mut [u8]) -> Self {
+        let size = buffer.len();
+        Self::new_in(FixedBuffer::borrowed(buffer), size)
+    }
+
+    /// Like `from_buffer`, but the allocator owns the buffer and frees it when dropped
+    #[cfg(feature = "std")]
+    pub fn from_boxed_buffer(buffer: Box<[u8]>) -> Self {
+        let size = buffer.len();
+        Self::new_in(FixedBuffer::owned(buffer), size)
+    }
+
+    /// New Bump allocator over `len` bytes of memory owned by someone else, like a mapping of a device buffer
+    ///
+    /// The memory is never released by the allocator, take it back with `into_raw_parts`.
+    ///
+    /// # Safety
+    /// `base` must be valid for reads and writes of `len` bytes, for as long as the allocator and the slices frozen
+    /// from it are in use, and nothing else may access that memory in the meantime.
+    pub unsafe fn from_raw_parts(base: *mut u8, len: usize) -> Self {
+        Self::new_in(FixedBuffer::from_raw_parts(base, len), len)
+    }
+}
+
+impl<S: PageSource> BumpAlloc<S> {
+    /// New Bump allocator over `size` bytes reserved from `source`
+    pub fn new_in(source: S, size: usize) -> Self {
+        Self::try_new_in(source, size).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new_in`, but returns an error instead of panicking when `source` can't reserve the address space
+    pub fn try_new_in(mut source: S, size: usize) -> Result<Self, AllocError> {
+        let base = source.reserve(size).map_err(|errno| AllocError { size, errno })?;
+        Ok(BumpAlloc {
+            address_space: size,
+            data_base: base,
+            top_base: base,
+            top_size: 0,
+            committed: if source.commits_on_reserve() { size } else { 0 },
+            overflow: OverflowPolicy::default(),
+            canaries: false,
+            last_canary: core::ptr::null_mut(),
+            drop_policy: DropPolicy::default(),
+            drop_hook: None,
+            generation: 0,
+            freezes: 0,
+            hooks: Hooks::default(),
+            high_water: 0,
+            dirty: 0,
+            reserved: 0,
+            #[cfg(feature = "std")]
+            registry: None,
+            #[cfg(feature = "std")]
+            checksums: false,
+            #[cfg(feature = "std")]
+            dedup: None,
+            drop_list: core::ptr::null_mut(),
+            source,
+        })
+    }
+
+    /// Puts a canary word after every frozen allocation in debug builds, and checks the last one on every freeze
+    ///
+    /// Overruns past the end of a frozen slice then panic on the next freeze instead of silently corrupting the
+    /// following allocation. Canaries take up space in the arena, and are left out in release builds.
+    pub fn with_canaries(mut self) -> Self {
+        self.canaries = cfg!(debug_assertions);
+        self
+    }
+
+    /// Sets what happens when the top vector outgrows the arena
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow = policy;
+        self
+    }
+
+    /// Keeps a record of every frozen allocation, so they can be listed with `frozen_slices`
+    #[cfg(feature = "std")]
+    pub fn with_registry(mut self) -> Self {
+        self.registry = Some(Vec::new());
+        self
+    }
+
+    /// Keeps a registry (see `with_registry`) with a CRC32 of every frozen allocation, so `verify_all` can tell if
+    /// any has changed since
+    ///
+    /// This suits long-lived arenas used as caches, whose contents are final once frozen. Allocations made by the
+    /// arena collections, `BumpAllocRef::alloc_with_drop` and the `Allocator` impl are written after they're frozen,
+    /// so they're left out.
+    #[cfg(feature = "std")]
+    pub fn with_checksums(mut self) -> Self {
+        self.registry.get_or_insert_with(Vec::new);
+        self.checksums = true;
+        self
+    }
+
+    /// Keeps an index of the contents of the slices frozen with `LiquidVecRef::freeze_dedup`, so identical ones are
+    /// only stored once
+    #[cfg(feature = "std")]
+    pub fn with_dedup(mut self) -> Self {
+        self.dedup = Some(std::collections::HashMap::new());
+        self
+    }
+
+    /// Sets the callbacks to make on freezes, commits and resets
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Sets what happens to the top vector when its `LiquidVecRef` is dropped without being frozen
+    pub fn with_drop_policy(mut self, policy: DropPolicy) -> Self {
+        self.drop_policy = policy;
+        self
+    }
+
+    /// Calls `hook` with the size in bytes of the top vector whenever a non-empty `LiquidVecRef` is dropped without
+    /// being frozen, before the drop policy is applied
+    pub fn with_drop_hook(mut self, hook: fn(usize)) -> Self {
+        self.drop_hook = Some(hook);
+        self
+    }
+
+    pub fn to_ref<'data>(&'data mut self) -> BumpAllocRef<'data> where S: 'static {
+        BumpAllocRef { ptr: self as *mut BumpAlloc<S> as *mut BumpAlloc<dyn PageSource>, _data: PhantomData }
+    }
+
+    /// ```compile_fail
+    /// use freeze::{BumpAlloc};
+    /// let mut allocb = BumpAlloc::new();
+    /// let mut alloc = allocb.to_ref();
+    /// let mut v1 = alloc.top();
+    /// v1.extend_from_slice(&[42]);
+    /// let slice = v1.freeze();
+    /// allocb.reset();
+    /// let _ = slice.len(); // should fail
+    /// ```
+    /// Throws away everything frozen so far and hands the used pages back to the source, rewinding to an empty arena
+    ///
+    /// This takes the `BumpAlloc` rather than a `BumpAllocRef`, so every frozen slice has to be gone before it's called.
+    /// Everything written so far is handed back, also what was given back to the top vector by `truncate` or a `Mark`
+    /// rollback. With OS pages the memory reads as zero again afterwards.
+    pub fn reset(&mut self) {
+        self.drop_values(self.data_base);
+        self.high_water = self.high_water.max(self.top_end());
+        self.dirty = self.dirty.max(self.top_end());
+        if let Some(on_reset) = self.hooks.on_reset {
+            on_reset(self.top_end());
+        }
+        let used = if self.source.commits_on_reserve() { self.dirty } else { self.committed };
+        unsafe { self.source.decommit(self.data_base, used) };
+        if !self.source.commits_on_reserve() {
+            self.committed = 0;
+        }
+        if self.source.zeroed() {
+            self.dirty = 0;
+        }
+        self.reserved = 0;
+        self.top_base = self.data_base;
+        self.top_size = 0;
+        self.last_canary = core::ptr::null_mut();
+        self.forget_records(self.data_base);
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// How many times the arena has been `reset`, which makes the `ArenaSpan`s from before stale
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// The frozen bytes `span` points at, or an error if it's from an earlier generation or they aren't all frozen
+    ///
+    /// Like `reset`, this takes the `BumpAlloc`, so the bytes can't be reached through a frozen slice at the same time.
+    pub fn resolve(&self, span: ArenaSpan) -> Result<&[u8], StaleHandle> {
+        self.frozen_range(span).map(|start| unsafe { core::slice::from_raw_parts(start, span.len) })
+    }
+
+    /// Like `resolve`, but for writing to the bytes
+    pub fn resolve_mut(&mut self, span: ArenaSpan) -> Result<&mut [u8], StaleHandle> {
+        self.frozen_range(span).map(|start| unsafe { core::slice::from_raw_parts_mut(start, span.len) })
+    }
+
+    fn frozen_range(&self, span: ArenaSpan) -> Result<*mut u8, StaleHandle> {
+        let frozen = unsafe { self.top_base.offset_from(self.data_base) as usize };
+        match span.offset.checked_add(span.len) {
+            Some(end) if end <= frozen && span.generation == self.generation => unsafe {
+                Ok(self.data_base.add(span.offset))
+            },
+            _ => Err(StaleHandle { span, generation: self.generation }),
+        }
+    }
+
+    /// Every frozen allocation in order, if the allocator keeps a registry (see `with_registry`)
+    ///
+    /// This takes the `BumpAlloc` like `reset`, so no frozen slice can be written to while they're read.
+    #[cfg(feature = "std")]
+    pub fn frozen_slices(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.registry.iter().flatten().map(|record| self.record_bytes(record))
+    }
+
+    /// The first allocation frozen with `tag`, see `LiquidVecRef::freeze_tagged`
+    #[cfg(feature = "std")]
+    pub fn find_by_tag(&self, tag: u32) -> Option<&[u8]> {
+        self.tags().find(|&(t, _)| t == tag).map(|(_, bytes)| bytes)
+    }
+
+    /// Every tagged allocation in order, with its tag
+    #[cfg(feature = "std")]
+    pub fn tags(&self) -> impl Iterator<Item = (u32, &[u8])> + '_ {
+        self.registry.iter().flatten()
+            .filter_map(|record| record.tag.map(|tag| (tag, self.record_bytes(record))))
+    }
+
+    /// Checks every frozen allocation against the checksum taken when it was frozen, see `with_checksums`
+    ///
+    /// Reports the first one that changed. This takes the `BumpAlloc` like `frozen_slices`.
+    #[cfg(feature = "std")]
+    pub fn verify_all(&self) -> Result<(), ChecksumMismatch> {
+        for record in self.registry.iter().flatten() {
+            let Some(expected) = record.crc else { continue };
+            let found = crate::Crc32::checksum(self.record_bytes(record));
+            if found != expected {
+                let span = ArenaSpan::new(record.offset, record.len).with_generation(self.generation);
+                return Err(ChecksumMismatch { span, expected, found });
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    fn record_bytes(&self, record: &Record) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.data_base.add(record.offset), record.len) }
+    }
+
+    /// The source the memory of this allocator comes from
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Dismantles the allocator without releasing its memory, returning the start and size of the reservation
+    ///
+    /// Whatever was frozen stays in place; `data_size` tells how much of the reservation was used. Values put in the
+    /// arena by `BumpAllocRef::alloc_with_drop` are dropped first, as they would be when the allocator is, and the
+    /// registry and dedup index are freed along with the source.
+    pub fn into_raw_parts(mut self) -> (*mut u8, usize) {
+        self.drop_values(self.data_base);
+        #[cfg(feature = "std")]
+        {
+            self.registry = None;
+            self.dedup = None;
+        }
+        let mut this = core::mem::ManuallyDrop::new(self);
+        unsafe { core::ptr::drop_in_place(&mut this.source) };
+        (this.data_base, this.address_space)
+    }
+
+    /// New allocator over a fresh reservation from a copy of the source, holding the same data and top vector
+    ///
+    /// Both arenas carry on independently afterwards. Values put in the arena by `BumpAllocRef::alloc_with_drop` are
+    /// copied as plain bytes and only dropped by the original. Panics if the memory can't be reserved or committed.
+    pub fn clone_contents(&self) -> Self where S: Clone {
+        let mut clone = Self::new_in(self.source.clone(), self.address_space);
+        let used = self.top_end();
+        clone.ensure(used);
+        unsafe {
+            core::ptr::copy_nonoverlapping(self.data_base, clone.data_base, used);
+            clone.top_base = clone.data_base.offset(self.top_base.offset_from(self.data_base));
+            if !self.last_canary.is_null() {
+                clone.last_canary = clone.data_base.offset(self.last_canary.offset_from(self.data_base));
+            }
+        }
+        clone.top_size = self.top_size;
+        clone.overflow = self.overflow;
+        clone.canaries = self.canaries;
+        clone.drop_policy = self.drop_policy;
+        clone.drop_hook = self.drop_hook;
+        clone.generation = self.generation;
+        clone.freezes = self.freezes;
+        clone.hooks = self.hooks;
+        clone.high_water = self.high_water;
+        clone.dirty = self.dirty;
+        clone.reserved = self.reserved;
+        #[cfg(feature = "std")]
+        {
+            clone.registry = self.registry.clone();
+            clone.checksums = self.checksums;
+            clone.dedup = self.dedup.clone();
+        }
+        clone
+    }
+
+    /// Copies everything allocated so far, the top vector included, into a `Box` and releases the arena
+    #[cfg(feature = "std")]
+    pub fn into_boxed_bytes(self) -> Box<[u8]> {
+        unsafe { core::slice::from_raw_parts(self.data_base, self.top_end()) }.into()
+    }
+}
+
+impl<S: ?Sized + PageSource> BumpAlloc<S> {
+    /// Offset of the end of the top vector from the start of the arena
+    #[inline(always)]
+    pub(crate) fn top_end(&self) -> usize {
+        unsafe { self.top_base.offset_from(self.data_base) as usize + self.top_size }
+    }
+
+    /// Makes sure `additional` bytes can be written after the top vector, panicking if they can't
+    #[inline(always)]
+    pub(crate) fn ensure(&mut self, additional: usize) {
+        if let Err(e) = self.try_ensure(additional) {
+            panic!("{e}")
+        }
+    }
+
+    /// Makes sure `additional` bytes can be written after the top vector
+    #[inline(always)]
+    pub(crate) fn try_ensure(&mut self, additional: usize) -> Result<(), CapacityError> {
+        if self.top_end().saturating_add(additional) > self.committed {
+            self.commit(additional)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Turns the top vector into a frozen allocation and starts a new one after it, returning the frozen start
+    #[inline(always)]
+    pub(crate) fn freeze_top(&mut self) -> *mut u8 {
+        let start = self.top_base;
+        self.freezes += 1;
+        if let Some(on_freeze) = self.hooks.on_freeze {
+            let offset = unsafe { start.offset_from(self.data_base) as usize };
+            on_freeze(ArenaSpan::new(offset, self.top_size).with_generation(self.generation));
+        }
+        #[cfg(feature = "std")]
+        if let Some(registry) = &mut self.registry {
+            let offset = unsafe { start.offset_from(self.data_base) as usize };
+            let crc = self.checksums.then(|| unsafe {
+                crate::Crc32::checksum(core::slice::from_raw_parts(start, self.top_size))
+            });
+            registry.push(Record { offset, len: self.top_size, tag: None, crc });
+        }
+        if self.canaries {
+            self.place_canary();
+        }
+        unsafe {
+            self.top_base = self.top_base.add(self.top_size);
+            self.top_size = 0;
+        }
+        start
+    }
+
+    /// Applies the drop hook and policy to a top vector that was dropped without being frozen
+    #[cold]
+    pub(crate) fn drop_top(&mut self) {
+        if let Some(hook) = self.drop_hook {
+            hook(self.top_size);
+        }
+        match self.drop_policy {
+            DropPolicy::Keep => {}
+            DropPolicy::Rollback => self.shrink_top(0),
+            DropPolicy::Freeze => { self.freeze_top(); }
+        }
+    }
+
+    /// Tags the record of the allocation frozen last
+    #[inline(always)]
+    #[allow(unused_variables)]
+    pub(crate) fn tag_last(&mut self, tag: u32) {
+        #[cfg(feature = "std")]
+        if let Some(record) = self.registry.as_mut().and_then(|registry| registry.last_mut()) {
+            record.tag = Some(tag);
+        }
+    }
+
+    /// Leaves the allocation frozen last out of `verify_all`, as it's written to after freezing by design
+    #[inline(always)]
+    pub(crate) fn unstamp_last(&mut self) {
+        #[cfg(feature = "std")]
+        if let Some(record) = self.registry.as_mut().and_then(|registry| registry.last_mut()) {
+            record.crc = None;
+        }
+    }
+
+    /// Runs the destructors of the values at or after `from`, most recent first, before their memory is given back
+    pub(crate) fn drop_values(&mut self, from: *mut u8) {
+        while !self.drop_list.is_null() && self.drop_list as *mut u8 >= from {
+            let node = self.drop_list;
+            unsafe {
+                self.drop_list = (*node).prev;
+                ((*node).drop)((*node).value);
+            }
+        }
+    }
+
+    /// Drops the records of the allocations starting at or after `from`, which are being given back to the arena
+    #[inline(always)]
+    #[allow(unused_variables)]
+    pub(crate) fn forget_records(&mut self, from: *mut u8) {
+        #[cfg(feature = "std")]
+        {
+            let offset = unsafe { from.offset_from(self.data_base) as usize };
+            if let Some(registry) = &mut self.registry {
+                registry.truncate(registry.partition_point(|record| record.offset < offset));
+            }
+            if let Some(dedup) = &mut self.dedup {
+                dedup.retain(|_, spans| {
+                    spans.retain(|&(start, _)| start < offset);
+                    !spans.is_empty()
+                });
+            }
+        }
+    }
+
+    /// A slice frozen by `freeze_dedup` holding the same bytes, or else the hash to index `bytes` under
+    #[cfg(feature = "std")]
+    pub(crate) fn find_duplicate(&self, bytes: &[u8]) -> Option<Result<*const u8, u64>> {
+        use std::hash::{Hash, Hasher};
+        let dedup = self.dedup.as_ref()?;
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        let hash = hasher.finish();
+        let found = dedup.get(&hash).into_iter().flatten().find_map(|&(offset, len)| unsafe {
+            let start = self.data_base.add(offset);
+            (core::slice::from_raw_parts(start, len) == bytes).then_some(start as *const u8)
+        });
+        Some(found.ok_or(hash))
+    }
+
+    /// Offset from which the arena has never been written, if its memory started out zeroed
+    #[inline(always)]
+    pub(crate) fn zeroed_from(&self) -> usize {
+        if self.source.zeroed() { self.dirty.max(self.top_end()) } else { usize::MAX }
+    }
+
+    /// Moves the start of the empty top vector up to the next multiple of `align`, skipping the bytes in between
+    pub(crate) fn align_top(&mut self, align: usize) {
+        debug_assert_eq!(self.top_size, 0);
+        let padding = self.top_base.align_offset(align);
+        self.ensure(padding);
+        self.top_base = unsafe { self.top_base.add(padding) };
+    }
+
+    /// Shrinks the top vector to `size` bytes, poisoning the released bytes with 0xDD in debug builds
+    #[inline(always)]
+    pub(crate) fn shrink_top(&mut self, size: usize) {
+        debug_assert!(size <= self.top_size);
+        self.high_water = self.high_water.max(self.top_end());
+        self.dirty = self.dirty.max(self.top_end());
+        #[cfg(debug_assertions)]
+        unsafe {
+            self.top_base.add(size).write_bytes(0xDD, self.top_size - size);
+        }
+        self.top_size = size;
+    }
+
+    /// Checks the canary after the previously frozen allocation, and puts one after the top vector
+    #[cold]
+    fn place_canary(&mut self) {
+        const CANARY: [u8; 8] = [0xCA, 0x4A, 0x27, 0xE5, 0xF7, 0xEE, 0x2E, 0x0F];
+        unsafe {
+            if !self.last_canary.is_null() && self.last_canary.cast::<[u8; 8]>().read_unaligned() != CANARY {
+                panic!("overrun detected: the canary after the allocation ending at offset {} was overwritten",
+                       self.last_canary.offset_from(self.data_base))
+            }
+            self.ensure(CANARY.len());
+            self.last_canary = self.top_base.add(self.top_size);
+            self.last_canary.cast::<[u8; 8]>().write_unaligned(CANARY);
+            self.top_size += CANARY.len();
+        }
+    }
+
+    /// Bytes of address space left after the top vector
+    #[inline(always)]
+    pub(crate) fn remaining(&self) -> usize {
+        self.address_space - self.top_end()
+    }
+
+    /// Verifies the top vector still lies in the committed part of the arena, before `op` touches it
+    ///
+    /// Only done with the `checked` feature or debug assertions. `ensure` keeps writes inside the reservation,
+    /// but it trusts the cursor, so this catches the cursor itself being corrupted.
+    #[inline(always)]
+    #[allow(unused_variables)]
+    pub(crate) fn check(&self, op: &str) {
+        #[cfg(any(feature = "checked", debug_assertions))]
+        {
+            let start = (self.top_base as usize).wrapping_sub(self.data_base as usize);
+            if start > self.committed || self.top_size > self.committed - start {
+                panic!("{op}: the top vector at [{start}, {start} + {}) lies outside the {} committed bytes of the arena",
+                       self.top_size, self.committed)
+            }
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn commit(&mut self, additional: usize) -> Result<(), CapacityError> {
+        let res = self.try_commit(additional);
+        if let (Err(e), OverflowPolicy::Panic) = (res, self.overflow) {
+            panic!("{e}")
+        }
+        res
+    }
+
+    fn try_commit(&mut self, additional: usize) -> Result<(), CapacityError> {
+        let needed = self.top_end().saturating_add(additional);
+        if needed > self.address_space {
+            let errno = if self.overflow == OverflowPolicy::Grow { self.grow(needed) } else { Err(0) };
+            if let Err(errno) = errno {
+                return Err(CapacityError { requested: additional, remaining: self.remaining(), errno })
+            }
+        }
+        let committed = needed.next_multiple_of(COMMIT_GRANULARITY).min(self.address_space);
+        unsafe {
+            if let Err(errno) = self.source.commit(self.data_base.add(self.committed), committed - self.committed) {
+                return Err(CapacityError { requested: additional, remaining: self.remaining(), errno })
+            }
+        }
+        if let (Some(on_reserve), true) = (self.hooks.on_reserve, committed > self.committed) {
+            on_reserve(self.committed, committed - self.committed);
+        }
+        self.committed = committed;
+        Ok(())
+    }
+
+    /// Extends the reservation in place to at least `needed` bytes, doubling it if possible
+    fn grow(&mut self, needed: usize) -> Result<(), i32> {
+        let shortfall = needed - self.address_space;
+        let res = unsafe {
+            self.source.grow(self.data_base, self.address_space, shortfall.max(self.address_space))
+                .map(|()| shortfall.max(self.address_space))
+                .or_else(|_| self.source.grow(self.data_base, self.address_space, shortfall).map(|()| shortfall))
+        };
+        let additional = res?;
+        self.address_space += additional;
+        if self.source.commits_on_reserve() {
+            if let Some(on_reserve) = self.hooks.on_reserve {
+                on_reserve(self.committed, self.address_space - self.committed);
+            }
+            self.committed = self.address_space;
+        }
+        Ok(())
+    }
+}
+
+impl<S: ?Sized + PageSource> Drop for BumpAlloc<S> {
+    fn drop(&mut self) {
+        self.drop_values(self.data_base);
+        unsafe {
+            self.source.release(self.data_base, self.address_space);
+        }
+    }
+}
+
+#[repr(transparent)]
+pub struct BumpAllocRef<'data> {
+    pub(crate) ptr: *mut BumpAlloc<dyn PageSource>,
+    _data: PhantomData<&'data ()>,
+}
+
+impl<'data> BumpAllocRef<'data> {
+    /// ```compile_fail
+    /// use freeze::{BumpAlloc};
+    /// let mut alloc = BumpAlloc::new();
+    /// let mut alloc = alloc.to_ref();
+    /// let mut v1 = alloc.top();
+    /// let mut v2 = alloc.top();
+    /// v1.extend_from_slice(&[1]); // borrowing alloc twice
+    /// v2.extend_from_slice(&[1]);
+    /// ```
+    /// Gets the (custom) Vec ref that's currently able to be modified
+    pub fn top<'alloc>(&'alloc mut self) -> LiquidVecRef<'alloc, 'data> {
+        unsafe {
+            LiquidVecRef {
+                alloc: self.ptr.as_mut().unwrap_unchecked(),
+                _data: PhantomData,
+            }
+        }
+    }
+
+    /// Gets the top vector after reserving room for `n` more bytes, see `LiquidVecRef::try_reserve`
+    pub fn top_with_capacity<'alloc>(&'alloc mut self, n: usize) -> Result<LiquidVecRef<'alloc, 'data>, CapacityError> {
+        let mut top = self.top();
+        top.try_reserve(n)?;
+        Ok(top)
+    }
+
+    /// ```compile_fail
+    /// use freeze::BumpAlloc;
+    /// let mut alloc = BumpAlloc::new();
+    /// let mut alloc = alloc.to_ref();
+    /// alloc.top().extend_from_slice(&[2]);
+    /// let b = alloc.top_of::<bool>()[0]; // not every byte is a valid bool
+    /// ```
+    /// Gets the top vector as a vector of `T`, aligning its start for `T` if it's still empty
+    ///
+    /// The top vector may still hold bytes written as another element type, which are then read back as `T`, so `T`
+    /// has to be `Pod`. Panics if those bytes don't line up with `T`.
+    pub fn top_of<'alloc, T: Pod>(&'alloc mut self) -> LiquidVecRef<'alloc, 'data, T> {
+        self.top_as()
+    }
+
+    /// `top_of` for any `Copy` type, which is only sound while the top vector is empty
+    fn top_as<'alloc, T: Copy>(&'alloc mut self) -> LiquidVecRef<'alloc, 'data, T> {
+        const { assert!(size_of::<T>() != 0, "zero-sized elements are not supported") }
+        unsafe {
+            let alloc = self.ptr.as_mut().unwrap_unchecked();
+            if alloc.top_size == 0 {
+                alloc.align_top(align_of::<T>());
+            }
+            assert!(alloc.top_base.cast::<T>().is_aligned() && alloc.top_size % size_of::<T>() == 0,
+                    "the unfrozen top vector can't be viewed as a vector of `{}`", core::any::type_name::<T>());
+            LiquidVecRef {
+                alloc,
+                _data: PhantomData,
+            }
+        }
+    }
+
+    /// Turns the most recently frozen slice back into the top vector, so more elements can be appended to it
+    ///
+    /// Panics if `slice` isn't the last allocation, or the top vector isn't empty. Like `top_of` this needs `T: Pod`,
+    /// as the elements are left in the top vector for whatever type views it next.
+    pub fn unfreeze_last<'alloc, T: Pod>(&'alloc mut self, slice: &'data mut [T]) -> LiquidVecRef<'alloc, 'data, T> {
+        let alloc = unsafe { self.ptr.as_mut().unwrap_unchecked() };
+        alloc.check("BumpAllocRef::unfreeze_last");
+        let start = slice.as_mut_ptr() as *mut u8;
+        let end = unsafe { start.add(core::mem::size_of_val(slice)) };
+        let canary = alloc.canaries && end == alloc.last_canary;
+        let frozen_end = if canary { unsafe { end.add(8) } } else { end };
+        assert!(frozen_end == alloc.top_base && alloc.top_size == 0,
+                "unfreeze_last: the slice is not the most recent allocation of this arena");
+        alloc.top_base = start;
+        alloc.top_size = core::mem::size_of_val(slice);
+        if canary {
+            alloc.last_canary = core::ptr::null_mut();
+        }
+        alloc.forget_records(start);
+        LiquidVecRef {
+            alloc,
+            _data: PhantomData,
+        }
+    }
+
+    #[allow(dead_code)]
+    unsafe fn data_range(&self) -> &[u8] {
+        let data_base = (*self.ptr).data_base;
+        core::slice::from_raw_parts(data_base, self.data_size())
+    }
+
+    #[allow(dead_code)]
+    unsafe fn data_range_mut(&mut self) -> &mut [u8] {
+        let data_base = (*self.ptr).data_base;
+        core::slice::from_raw_parts_mut(data_base, self.data_size())
+    }
+
+    /// The total number of data bytes allocated over the lifetime of the allocator
+    pub fn data_size(&self) -> usize {
+        unsafe {
+            (*self.ptr).top_end()
+        }
+    }
+
+    /// Whether `ptr` points into the bytes allocated from this arena so far, the top vector included
+    pub fn contains(&self, ptr: *const u8) -> bool {
+        let alloc = unsafe { &*self.ptr };
+        (alloc.data_base as usize..alloc.data_base as usize + alloc.top_end()).contains(&(ptr as usize))
+    }
+
+    /// Offset in bytes of `slice` from the start of the arena, or `None` if it doesn't lie in the allocated bytes
+    pub fn offset_of<T>(&self, slice: &[T]) -> Option<usize> {
+        let alloc = unsafe { &*self.ptr };
+        let offset = (slice.as_ptr() as usize).checked_sub(alloc.data_base as usize)?;
+        (offset.checked_add(core::mem::size_of_val(slice))? <= alloc.top_end()).then_some(offset)
+    }
+
+    /// Takes a fresh snapshot of the allocator's usage; finding out how much is resident takes a system call
+    pub fn stats(&self) -> Stats {
+        let alloc = unsafe { &*self.ptr };
+        let high_water = alloc.high_water.max(alloc.top_end());
+        Stats {
+            allocated: alloc.top_end(),
+            frozen: unsafe { alloc.top_base.offset_from(alloc.data_base) as usize },
+            freezes: alloc.freezes,
+            high_water,
+            address_space: alloc.address_space,
+            resident: unsafe { alloc.source.resident(alloc.data_base, high_water.min(alloc.committed)) },
+        }
+    }
+
+    /// Bytes that can still be allocated before the reserved address space runs out
+    pub fn remaining(&self) -> usize {
+        unsafe { (*self.ptr).remaining() }
+    }
+
+    /// More than half of the address space is already used
+    pub fn dangerous(&self) -> bool {
+        unsafe {
+            (self.data_size() + size_of::<BumpAlloc>()) > (*self.ptr).address_space/2
+        }
+    }
+
+    /// Remembers the current state of the arena so that everything allocated after it can be rolled back
+    ///
+    /// Allocations meant to be rolled back go through `Mark::arena`, which ties the slices they freeze to the mark.
+    /// Panics if the top vector isn't empty, as the child arena could change it in ways a rollback can't undo.
+    pub fn mark(&mut self) -> Mark<'_> {
+        let alloc = unsafe { &*self.ptr };
+        assert!(alloc.top_size == 0, "BumpAllocRef::mark: the top vector isn't empty");
+        Mark {
+            ptr: self.ptr,
+            top_base: alloc.top_base,
+            last_canary: alloc.last_canary,
+            _parent: PhantomData,
+        }
+    }
+
+    /// ```compile_fail
+    /// use freeze::{BumpAlloc};
+    /// let mut alloc = BumpAlloc::new();
+    /// let mut alloc = alloc.to_ref();
+    /// let slice = alloc.scope(|child| {
+    ///     let mut v1 = child.top();
+    ///     v1.extend_from_slice(&[42]);
+    ///     v1.freeze()  // should fail, the slice is reclaimed with the scope
+    /// });
+    /// ```
+    /// Runs `f` with a child arena whose allocations are all given back when it returns
+    ///
+    /// Slices frozen before the scope stay valid; those frozen inside it can't leave the closure. Panics if the top
+    /// vector isn't empty, see `mark`.
+    pub fn scope<R>(&mut self, f: impl FnOnce(&mut BumpAllocRef<'_>) -> R) -> R {
+        let mut mark = self.mark();
+        let res = f(&mut mark.arena());
+        mark.rollback();
+        res
+    }
+
+    /// Formats `args` straight into the arena and freezes the result, see `freeze_format!`
+    ///
+    /// Panics if the top vector isn't empty, the arena is exhausted, or a formatting trait returns an error.
+    pub fn format(&mut self, args: core::fmt::Arguments<'_>) -> &'data mut str {
+        let mut v = self.empty_top("BumpAllocRef::format");
+        if core::fmt::Write::write_fmt(&mut v, args).is_err() {
+            panic!("BumpAllocRef::format: the arena is exhausted or a formatting trait returned an error");
+        }
+        unsafe { v.freeze_str_unchecked() }
+    }
+
+    /// Moves `value` into the arena
+    ///
+    /// Panics if the top vector isn't empty or the arena is exhausted, like the other `alloc_` methods.
+    #[inline(always)]
+    pub fn alloc<T: Copy>(&mut self, value: T) -> &'data mut T {
+        self.alloc_with(|| value)
+    }
+
+    /// Makes room for a `T` in the arena, then calls `f` to produce it
+    pub fn alloc_with<T: Copy>(&mut self, f: impl FnOnce() -> T) -> &'data mut T {
+        let mut v = self.empty_top::<T>("BumpAllocRef::alloc_with");
+        v.reserve(1);
+        v.push(f());
+        &mut v.freeze()[0]
+    }
+
+    /// Moves a value that needs dropping into the arena, recording its destructor
+    ///
+    /// The value is dropped when its memory is given back: on `BumpAlloc::reset`, `Mark::rollback` or at the end of a
+    /// `scope` it was allocated in, and when the `BumpAlloc` is dropped or turned into an `OwnedAlloc`.
+    /// Panics if the top vector isn't empty or the arena is exhausted, like the other `alloc_` methods.
+    pub fn alloc_with_drop<T>(&mut self, value: T) -> &'data mut T {
+        unsafe fn drop_value<T>(value: *mut u8) {
+            unsafe { value.cast::<T>().drop_in_place() }
+        }
+
+        let node = if core::mem::needs_drop::<T>() { size_of::<DropNode>() } else { 0 };
+        let offset = node.next_multiple_of(align_of::<T>());
+        let mut v = self.empty_top::<u8>("BumpAllocRef::alloc_with_drop");
+        v.alloc.align_top(align_of::<T>().max(align_of::<DropNode>()));
+        v.reserve(offset + size_of::<T>());
+        unsafe {
+            v.set_len(offset + size_of::<T>());
+            let alloc: *mut BumpAlloc<dyn PageSource> = v.alloc;
+            let base = v.freeze().as_mut_ptr();
+            (*alloc).unstamp_last();
+            let value_ptr = base.add(offset).cast::<T>();
+            value_ptr.write(value);
+            if node != 0 {
+                base.cast::<DropNode>().write(DropNode { prev: (*alloc).drop_list, value: value_ptr.cast(), drop: drop_value::<T> });
+                (*alloc).drop_list = base.cast();
+            }
+            &mut *value_ptr
+        }
+    }
+
+    /// Copies a slice into the arena
+    pub fn alloc_slice_copy<T: Copy>(&mut self, slice: &[T]) -> &'data mut [T] {
+        let mut v = self.empty_top::<T>("BumpAllocRef::alloc_slice_copy");
+        v.extend_from_slice(slice);
+        v.freeze()
+    }
+
+    /// A new slice of `n` copies of `value`
+    pub fn alloc_slice_fill_copy<T: Copy>(&mut self, n: usize, value: T) -> &'data mut [T] {
+        let mut v = self.empty_top::<T>("BumpAllocRef::alloc_slice_fill_copy");
+        v.resize(n, value);
+        v.freeze()
+    }
+
+    /// Collects an iterator straight into a new slice
+    ///
+    /// Room for the iterator's lower size hint is reserved up front, so iterators that know their length, like
+    /// `slice.iter().copied()`, are checked against the arena once rather than per element.
+    pub fn collect_frozen<T: Copy>(&mut self, iter: impl IntoIterator<Item = T>) -> &'data mut [T] {
+        let iter = iter.into_iter();
+        let mut v = self.empty_top::<T>("BumpAllocRef::collect_frozen");
+        v.reserve(iter.size_hint().0);
+        v.extend(iter);
+        v.freeze()
+    }
+
+    /// Copies the parts one after the other into a single new slice
+    pub fn concat<T: Copy>(&mut self, parts: &[&[T]]) -> &'data mut [T] {
+        let mut v = self.empty_top::<T>("BumpAllocRef::concat");
+        v.reserve(parts.iter().map(|part| part.len()).sum());
+        for part in parts {
+            v.extend_from_slice(part);
+        }
+        v.freeze()
+    }
+
+    /// Copies the parts into a single new slice, with `sep` between each two
+    pub fn join<T: Copy>(&mut self, sep: &[T], parts: &[&[T]]) -> &'data mut [T] {
+        let mut v = self.empty_top::<T>("BumpAllocRef::join");
+        v.reserve(parts.iter().map(|part| part.len()).sum::<usize>() + sep.len() * parts.len().saturating_sub(1));
+        for (i, part) in parts.iter().enumerate() {
+            if i != 0 {
+                v.extend_from_slice(sep);
+            }
+            v.extend_from_slice(part);
+        }
+        v.freeze()
+    }
+
+    /// Copies each part into the arena, then a table of slices pointing at the copies, all frozen
+    ///
+    /// Suits tokenizers returning a list of tokens from one call. The copies are laid out one after the other, each
+    /// preceded by its length, which is read back to build the table so no other memory is needed.
+    pub fn freeze_group<'a>(&mut self, parts: impl IntoIterator<Item = &'a [u8]>) -> &'data mut [&'data [u8]] {
+        const HEADER: usize = size_of::<usize>();
+        let mut v = self.empty_top::<u8>("BumpAllocRef::freeze_group");
+        let mut count = 0;
+        for part in parts {
+            v.reserve(HEADER + part.len());
+            v.extend_from_slice(&part.len().to_ne_bytes());
+            v.extend_from_slice(part);
+            count += 1;
+        }
+        let mut blob: &'data [u8] = v.freeze();
+        let mut table = self.empty_top::<&'data [u8]>("BumpAllocRef::freeze_group");
+        table.reserve(count);
+        while let Some((len, rest)) = blob.split_first_chunk::<HEADER>() {
+            let (part, rest) = rest.split_at(usize::from_ne_bytes(*len));
+            table.push(part);
+            blob = rest;
+        }
+        table.freeze()
+    }
+
+    /// Copies a string into the arena
+    #[inline(always)]
+    pub fn alloc_str(&mut self, s: &str) -> &'data mut str {
+        unsafe { core::str::from_utf8_unchecked_mut(self.alloc_slice_copy(s.as_bytes())) }
+    }
+
+    /// The top vector as a vector of `T`, for an `op` that allocates and freezes in one step
+    ///
+    /// Panics if the top vector isn't empty, as the allocation would take its elements along.
+    pub(crate) fn empty_top<'alloc, T: Copy>(&'alloc mut self, op: &str) -> LiquidVecRef<'alloc, 'data, T> {
+        assert!(unsafe { (*self.ptr).top_size } == 0, "{op}: the top vector isn't empty");
+        self.top_as()
+    }
+}
+
+/// Formats into a `BumpAllocRef` like `format!`, returning a frozen `&mut str`
+///
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use freeze::{BumpAlloc, freeze_format};
+/// let mut alloc = BumpAlloc::new();
+/// let mut alloc = alloc.to_ref();
+/// let s = freeze_format!(alloc, "{}-{}", 1, 2);
+/// assert_eq!(s, "1-2");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! freeze_format {
+    ($alloc:expr, $($arg:tt)*) => {
+        $alloc.format(::core::format_args!($($arg)*))
+    };
+}
+
+/// Builds a frozen slice in a `BumpAllocRef` like `vec!`, from a list of elements or `n` copies of one
+///
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use freeze::{BumpAlloc, liquid_vec};
+/// let mut alloc = BumpAlloc::new();
+/// let mut alloc = alloc.to_ref();
+/// let s1 = liquid_vec![alloc; 1u16, 2, 3];
+/// let s2 = liquid_vec![alloc; 0u8; 4];
+/// assert_eq!((&*s1, &*s2), (&[1, 2, 3][..], &[0; 4][..]));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! liquid_vec {
+    ($alloc:expr; $elem:expr; $n:expr) => {
+        $alloc.alloc_slice_fill_copy($n, $elem)
+    };
+    ($alloc:expr; $($x:expr),* $(,)?) => {
+        $alloc.alloc_slice_copy(&[$($x),*])
+    };
+}
+
+/// A saved state of a `BumpAllocRef`, see `BumpAllocRef::mark`
+///
+/// Dropping the mark keeps whatever was allocated after it, `rollback` gives that space back to the arena.
+pub struct Mark<'m> {
+    ptr: *mut BumpAlloc<dyn PageSource>,
+    top_base: *mut u8,
+    last_canary: *mut u8,
+    _parent: PhantomData<&'m mut ()>,
+}
+
+impl<'m> Mark<'m> {
+    /// The arena to allocate from after the mark; what it freezes lives only as long as this borrow of the mark
+    pub fn arena(&mut self) -> BumpAllocRef<'_> {
+        BumpAllocRef { ptr: self.ptr, _data: PhantomData }
+    }
+
+    /// ```compile_fail
+    /// use freeze::{BumpAlloc};
+    /// let mut allocb = BumpAlloc::new();
+    /// let mut alloc = allocb.to_ref();
+    /// let mut mark = alloc.mark();
+    /// let mut arena = mark.arena();
+    /// let mut v1 = arena.top();
+    /// v1.extend_from_slice(&[42]);
+    /// let slice = v1.freeze();
+    /// mark.rollback();
+    /// let _ = slice.len(); // should fail
+    /// ```
+    /// Rewinds the arena to the mark, throwing away everything allocated after it
+    pub fn rollback(self) {
+        let alloc = unsafe { &mut *self.ptr };
+        alloc.check("Mark::rollback");
+        unsafe {
+            alloc.top_size = alloc.top_base.add(alloc.top_size).offset_from(self.top_base) as usize;
+        }
+        alloc.drop_values(self.top_base);
+        alloc.top_base = self.top_base;
+        alloc.shrink_top(0);
+        alloc.last_canary = self.last_canary;
+        alloc.forget_records(self.top_base);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::sys;
+
+    #[test]
+    fn try_new() {
+        assert!(BumpAlloc::try_new_with_address_space(20).is_ok());
+
+        let err = BumpAlloc::try_new_with_address_space(62).err().unwrap();
+        assert_eq!(err.size(), 1 << 62);
+        assert_ne!(err.errno(), 0);
+
+        let err = BumpAlloc::try_new_with_address_space(200).err().unwrap();
+        assert_eq!(err.errno(), sys::EINVAL);
+    }
+
+    #[test]
+    fn from_buffer() {
+        let buffer: &'static mut [u8] = Box::leak(Box::new([0; 64]));
+        let mut alloc = BumpAlloc::from_buffer(buffer);
+        let mut alloc = alloc.to_ref();
+
+        let s1: &mut [u8] = {
+            let mut v1 = alloc.top();
+            v1.extend_from_slice(&[1; 60]);
+            v1.freeze()
+        };
+        let s2: &mut [u16] = {
+            let mut v2 = alloc.top_of();
+            v2.extend_one(2);
+            v2.freeze()
+        };
+
+        assert_eq!(s1, [1; 60]);
+        assert_eq!(s2, [2]);
+        assert!(alloc.data_size() <= 64);
+    }
+
+    #[test]
+    #[should_panic(expected = "BumpAlloc exhausted")]
+    fn from_boxed_buffer_exhausted() {
+        let mut alloc = BumpAlloc::from_boxed_buffer(vec![0; 64].into_boxed_slice());
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(&[1; 60]);
+        v1.extend_from_within(..5);
+    }
+
+    #[test]
+    #[cfg(any(feature = "checked", debug_assertions))]
+    #[should_panic(expected = "LiquidVecRef::extend_from_slice: the top vector at [0, 0 + 8192) lies outside")]
+    fn checked() {
+        let mut alloc = BumpAlloc::new_with_address_space(12);
+        alloc.top_size = 1 << 13;
+        let mut alloc = alloc.to_ref();
+        alloc.top().extend_from_slice(&[1]);
+    }
+
+    #[test]
+    fn try_extend() {
+        let mut alloc = BumpAlloc::new_with_address_space(12);
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.try_extend_from_slice(&[1; 4000]).unwrap();
+        v1.try_extend_from_within(..90).unwrap();
+        let err = v1.try_extend_from_within(..10).unwrap_err();
+        assert_eq!((err.requested(), err.remaining(), err.errno()), (10, 6, 0));
+        v1.try_extend_one(2).unwrap();
+        assert_eq!(v1.len(), 4091);
+        assert!(v1.try_extend_from_slice(&[3; 6]).is_err());
+        assert_eq!(v1.len(), 4091);
+    }
+
+    /// Hands out part of a (leaked) heap buffer, and can grow up to the end of it
+    struct Growable(usize);
+
+    unsafe impl PageSource for Growable {
+        fn reserve(&mut self, size: usize) -> Result<*mut u8, i32> {
+            assert!(size <= self.0);
+            Ok(Box::leak(vec![0u8; self.0].into_boxed_slice()).as_mut_ptr())
+        }
+        fn commits_on_reserve(&self) -> bool {
+            true
+        }
+        unsafe fn commit(&mut self, _ptr: *mut u8, _len: usize) -> Result<(), i32> {
+            Ok(())
+        }
+        unsafe fn decommit(&mut self, _ptr: *mut u8, _len: usize) {}
+        unsafe fn release(&mut self, ptr: *mut u8, _size: usize) {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, self.0)));
+        }
+        unsafe fn grow(&mut self, _ptr: *mut u8, size: usize, additional: usize) -> Result<(), i32> {
+            if size + additional > self.0 { Err(sys::EINVAL) } else { Ok(()) }
+        }
+    }
+
+    #[test]
+    fn overflow_grow() {
+        let mut alloc = BumpAlloc::new_in(Growable(100), 16).with_overflow_policy(OverflowPolicy::Grow);
+        let mut alloc = alloc.to_ref();
+        let s1: &mut [u8] = {
+            let mut v1 = alloc.top();
+            v1.extend_from_slice(&[1; 20]);
+            v1.extend_from_slice(&[2; 20]);
+            v1.freeze()
+        };
+        let mut v2 = alloc.top();
+        v2.extend_from_slice(&[3; 20]);
+        v2.try_extend_from_slice(&[4; 30]).unwrap();
+        assert_eq!(v2.try_extend_from_slice(&[5; 20]).unwrap_err().errno(), sys::EINVAL);
+        assert_eq!(v2.len(), 50);
+        assert_eq!(s1[19..21], [1, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "BumpAlloc exhausted: 20 more bytes requested with 16 bytes left")]
+    fn overflow_panic() {
+        let mut alloc = BumpAlloc::new_in(Growable(100), 16).with_overflow_policy(OverflowPolicy::Panic);
+        let mut alloc = alloc.to_ref();
+        let _ = alloc.top().try_extend_from_slice(&[1; 20]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "overrun detected: the canary after the allocation ending at offset 13 was overwritten")]
+    fn canaries() {
+        let mut alloc = BumpAlloc::new().with_canaries();
+        let mut alloc = alloc.to_ref();
+        let s1 = {
+            let mut v1 = alloc.top();
+            v1.extend_from_slice(&[1, 2, 3]);
+            v1.freeze()
+        };
+        let s2 = {
+            let mut v2 = alloc.top();
+            v2.extend_from_slice(&[4, 5]);
+            v2.freeze()
+        };
+        assert_eq!((s1.len(), s2.len()), (3, 2));
+        unsafe { s2.as_mut_ptr().add(2).write(0) };
+        alloc.top().freeze();
+    }
+
+    #[test]
+    fn raw_parts() {
+        let mut buffer = vec![0u8; 1 << 10];
+        let shared = std::rc::Rc::new(());
+        let (base, len) = {
+            let mut alloc = unsafe { BumpAlloc::from_raw_parts(buffer.as_mut_ptr(), buffer.len()) }.with_registry();
+            let mut alloc_ref = alloc.to_ref();
+            let mut v1 = alloc_ref.top();
+            v1.extend_from_slice(&[1, 2, 3]);
+            v1.freeze();
+            assert_eq!(alloc_ref.data_size(), 3);
+            alloc_ref.alloc_with_drop(shared.clone());
+            assert_eq!(std::rc::Rc::strong_count(&shared), 2);
+            alloc.into_raw_parts()
+        };
+        assert_eq!((base, len), (buffer.as_mut_ptr(), 1 << 10));
+        assert_eq!(buffer[..4], [1, 2, 3, 0]);
+        assert_eq!(std::rc::Rc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    fn reset() {
+        let mut alloc = BumpAlloc::new();
+        for round in 0..3u8 {
+            let mut alloc_ref = alloc.to_ref();
+            let mut v1 = alloc_ref.top();
+            assert!(v1.is_empty());
+            v1.extend_from_slice(&[round; 100]);
+            assert_eq!(v1.freeze(), [round; 100]);
+            assert_eq!(alloc_ref.data_size(), 100);
+            alloc.reset();
+            #[cfg(target_os = "linux")]
+            assert_eq!(unsafe { *alloc.data_base }, 0);
+        }
+
+        let mut alloc_ref = alloc.to_ref();
+        let mut v1 = alloc_ref.top();
+        v1.extend_from_slice(&[7; 20000]);
+        v1.truncate(10);
+        v1.freeze();
+        alloc.reset();
+        let mut alloc_ref = alloc.to_ref();
+        let mut v2 = alloc_ref.top();
+        v2.extend_zeroed(20000);
+        assert!(v2.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn mark() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(&[1, 2]);
+        let s1 = v1.freeze();
+
+        let mut mark = alloc.mark();
+        let mut arena = mark.arena();
+        let mut v2 = arena.top();
+        v2.extend_from_slice(&[3, 4, 5]);
+        v2.freeze();
+        arena.top().extend_from_slice(&[6]);
+        mark.rollback();
+        assert_eq!(alloc.data_size(), 2);
+
+        let mut mark = alloc.mark();
+        let mut arena = mark.arena();
+        let mut v3 = arena.top();
+        v3.extend_from_slice(&[7]);
+        v3.freeze();
+        assert_eq!(alloc.data_size(), 3);
+        assert_eq!(s1, [1, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "BumpAllocRef::mark: the top vector isn't empty")]
+    fn mark_non_empty_top() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        alloc.top().extend_from_slice(b"header");
+        let mut mark = alloc.mark();
+        mark.arena().top().truncate(0);
+        mark.rollback();
+    }
+
+    #[test]
+    fn scope() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"kept");
+        let s1 = v1.freeze();
+        let sum = alloc.scope(|child| {
+            let mut v2 = child.top();
+            v2.extend_from_slice(&[1, 2, 3]);
+            let s2 = v2.freeze();
+            let inner = child.scope(|grandchild| {
+                grandchild.top().extend_from_slice(&[4]);
+                grandchild.data_size()
+            });
+            assert_eq!(inner, 8);
+            s2.iter().sum::<u8>()
+        });
+        assert_eq!(sum, 6);
+        assert_eq!(alloc.data_size(), 4);
+        assert_eq!(s1, b"kept");
+    }
+
+    #[test]
+    fn unfreeze_last() {
+        let mut alloc = BumpAlloc::new().with_canaries();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top_of::<u16>();
+        v1.extend_from_slice(&[1, 2]);
+        let s1 = v1.freeze();
+        let mut v1 = alloc.unfreeze_last(s1);
+        v1.extend_one(3);
+        assert_eq!(v1.freeze(), [1, 2, 3]);
+        let mut v2 = alloc.top();
+        v2.extend_one(4);
+        v2.freeze();
+        assert_eq!(alloc.top().freeze(), []);
+    }
+
+    #[test]
+    #[should_panic(expected = "not the most recent allocation")]
+    fn unfreeze_not_last() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_one(1);
+        let s1 = v1.freeze();
+        let mut v2 = alloc.top();
+        v2.extend_one(2);
+        v2.freeze();
+        alloc.unfreeze_last(s1);
+    }
+
+    #[test]
+    fn clone_contents() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc_ref = alloc.to_ref();
+        let mut v1 = alloc_ref.top();
+        v1.extend_from_slice(b"prefix ");
+        v1.freeze();
+        alloc_ref.top().extend_from_slice(b"and ");
+
+        let mut fork = alloc.clone_contents();
+        alloc.to_ref().top().extend_from_slice(b"one");
+        fork.to_ref().top().extend_from_slice(b"two");
+        assert_eq!(&*fork.to_ref().top(), b"and two");
+        assert_eq!(&*alloc.into_boxed_bytes(), b"prefix and one");
+        assert_eq!(&*fork.into_boxed_bytes(), b"prefix and two");
+    }
+
+    #[test]
+    fn registry() {
+        let mut alloc = BumpAlloc::new().with_registry().with_canaries();
+        let mut alloc_ref = alloc.to_ref();
+        for word in ["one", "two", "three"] {
+            let mut v1 = alloc_ref.top();
+            v1.extend_from_slice(word.as_bytes());
+            v1.freeze();
+        }
+        let mut v1 = alloc_ref.top_of::<u32>();
+        v1.extend_one(4);
+        let s1 = v1.freeze();
+        alloc_ref.unfreeze_last(s1).freeze();
+        alloc_ref.scope(|child| {
+            let mut v2 = child.top();
+            v2.extend_one(5);
+            v2.freeze();
+        });
+        let slices: Vec<_> = alloc.frozen_slices().collect();
+        assert_eq!(slices, [&b"one"[..], b"two", b"three", &4u32.to_ne_bytes()]);
+        alloc.reset();
+        assert_eq!(alloc.frozen_slices().count(), 0);
+    }
+
+    #[test]
+    fn tags() {
+        let mut alloc = BumpAlloc::new().with_registry();
+        let mut alloc_ref = alloc.to_ref();
+        for (tag, name) in [(1, "symbols"), (2, "strings")] {
+            let mut v1 = alloc_ref.top();
+            v1.extend_from_slice(name.as_bytes());
+            v1.freeze_tagged(tag);
+            alloc_ref.top().extend_from_slice(b"untagged");
+            alloc_ref.top().freeze();
+        }
+        assert_eq!(alloc.find_by_tag(2), Some(&b"strings"[..]));
+        assert_eq!(alloc.find_by_tag(3), None);
+        assert_eq!(alloc.tags().collect::<Vec<_>>(), [(1, &b"symbols"[..]), (2, b"strings")]);
+        assert_eq!(alloc.frozen_slices().count(), 4);
+    }
+
+    #[test]
+    fn checksums() {
+        let mut alloc = BumpAlloc::new().with_checksums();
+        let mut alloc_ref = alloc.to_ref();
+        let s1 = alloc_ref.alloc_slice_copy(b"cached");
+        let mut v1 = alloc_ref.top();
+        v1.extend_from_slice(b"entry");
+        let span = v1.freeze_span();
+        alloc_ref.alloc_with_drop(String::from("written after freezing")).push('!');
+        s1[0] = b'C';
+        alloc_ref.alloc_str("last");
+        assert_eq!(alloc.frozen_slices().count(), 4);
+        let err = alloc.verify_all().unwrap_err();
+        assert_eq!((err.span().offset(), err.span().len(), err.expected()), (0, 6, crate::Crc32::checksum(b"cached")));
+        alloc.resolve_mut(span).unwrap()[0] = b'E';
+        alloc.resolve_mut(ArenaSpan::new(0, 1)).unwrap()[0] = b'c';
+        assert_eq!(alloc.verify_all().unwrap_err().span(), span);
+    }
+
+    #[test]
+    fn spans() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc_ref = alloc.to_ref();
+        let mut v1 = alloc_ref.top();
+        v1.extend_from_slice(b"header");
+        let header = v1.freeze_span();
+        let mut v2 = alloc_ref.top_of::<u32>();
+        v2.extend_from_slice(&[1, 2]);
+        let body = v2.freeze_span();
+        alloc_ref.top().extend_from_slice(b"liquid");
+        assert_eq!(header, ArenaSpan::new(0, 6));
+        assert_eq!((body.offset(), body.len()), (8, 8));
+
+        assert_eq!(alloc.resolve(header), Ok(&b"header"[..]));
+        alloc.resolve_mut(header).unwrap()[0] = b'H';
+        assert_eq!(alloc.resolve(ArenaSpan::new(0, 1)), Ok(&b"H"[..]));
+        assert!(alloc.resolve(ArenaSpan::new(14, 4)).is_err());
+        assert!(alloc.resolve(ArenaSpan::new(usize::MAX, 2)).is_err());
+    }
+
+    #[test]
+    fn stale_spans() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc_ref = alloc.to_ref();
+        let mut v1 = alloc_ref.top();
+        v1.extend_from_slice(b"old");
+        let old = v1.freeze_span();
+        alloc.reset();
+        let mut alloc_ref = alloc.to_ref();
+        let mut v2 = alloc_ref.top();
+        v2.extend_from_slice(b"new");
+        let new = v2.freeze_span();
+        assert_eq!((old.generation(), new.generation(), alloc.generation()), (0, 1, 1));
+        assert_eq!(alloc.resolve(new), Ok(&b"new"[..]));
+        let err = alloc.resolve(old).unwrap_err();
+        assert_eq!(err.to_string(), "stale ArenaSpan: made in generation 0 of an arena now in generation 1");
+    }
+
+    #[test]
+    fn offset_of() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"abc");
+        let s1 = v1.freeze();
+        let mut v2 = alloc.top_of::<u16>();
+        v2.extend_from_slice(&[1, 2]);
+        let s2 = v2.freeze();
+        let outside = [0u8; 4];
+        assert!(alloc.contains(&s1[2]) && alloc.contains(s2.as_ptr().cast()));
+        assert!(!alloc.contains(outside.as_ptr()));
+        assert!(!alloc.contains(s1.as_ptr().wrapping_add(8)));
+        assert_eq!(alloc.offset_of(&s1[1..]), Some(1));
+        assert_eq!(alloc.offset_of(s2), Some(4));
+        assert_eq!(alloc.offset_of(&outside), None);
+    }
+
+    #[test]
+    fn stats() {
+        let mut alloc = BumpAlloc::new_with_address_space(24);
+        let mut alloc_ref = alloc.to_ref();
+        let mut v1 = alloc_ref.top();
+        v1.extend_from_slice(&[1; 5000]);
+        v1.freeze();
+        let mut v2 = alloc_ref.top();
+        v2.extend_from_slice(&[2; 100]);
+        v2.discard();
+        alloc_ref.top().extend_from_slice(&[3; 10]);
+        let stats = alloc_ref.stats();
+        assert_eq!((stats.allocated, stats.frozen, stats.freezes), (5010, 5000, 1));
+        assert_eq!((stats.high_water, stats.address_space), (5100, 1 << 24));
+        #[cfg(all(unix, not(feature = "rustix")))]
+        assert_eq!(stats.resident, Some(5100usize.next_multiple_of(sys::page_size())));
+
+        alloc.reset();
+        let stats = alloc.to_ref().stats();
+        assert_eq!((stats.allocated, stats.high_water), (0, 5100));
+    }
+
+    #[test]
+    fn hooks() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static FROZEN: AtomicUsize = AtomicUsize::new(0);
+        static RESERVED: AtomicUsize = AtomicUsize::new(0);
+        static RESET: AtomicUsize = AtomicUsize::new(0);
+
+        let hooks = Hooks {
+            on_freeze: Some(|span| { FROZEN.fetch_add(span.len(), Ordering::Relaxed); }),
+            on_reserve: Some(|offset, size| {
+                assert_eq!(offset, 16);
+                RESERVED.fetch_add(size, Ordering::Relaxed);
+            }),
+            on_reset: Some(|used| { RESET.store(used, Ordering::Relaxed); }),
+        };
+        let mut alloc = BumpAlloc::new_in(Growable(100), 16)
+            .with_overflow_policy(OverflowPolicy::Grow)
+            .with_hooks(hooks);
+        let mut alloc_ref = alloc.to_ref();
+        let mut v1 = alloc_ref.top();
+        v1.extend_from_slice(&[1; 10]);
+        v1.freeze();
+        let mut v2 = alloc_ref.top();
+        v2.extend_from_slice(&[2; 10]);
+        v2.freeze();
+        alloc.reset();
+        assert_eq!(FROZEN.load(Ordering::Relaxed), 20);
+        assert_eq!(RESERVED.load(Ordering::Relaxed), 16);
+        assert_eq!(RESET.load(Ordering::Relaxed), 20);
+    }
+
+    #[test]
+    fn format() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let name = "arena";
+        let s1 = freeze_format!(alloc, "hello {name}");
+        let s2 = freeze_format!(&mut alloc, "{:>4}|{:#x}", 7, 255);
+        s1.make_ascii_uppercase();
+        assert_eq!((&*s1, &*s2), ("HELLO ARENA", "   7|0xff"));
+        assert_eq!(alloc.data_size(), 20);
+    }
+
+    #[test]
+    fn alloc() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let byte = alloc.alloc(1u8);
+        let pair = alloc.alloc_with(|| (2u16, 3u64));
+        let slice = alloc.alloc_slice_copy(&[4u32, 5]);
+        let s = alloc.alloc_str("six");
+        pair.0 += 10;
+        s.make_ascii_uppercase();
+        assert_eq!((*byte, *pair, &*slice, &*s), (1, (12, 3), &[4, 5][..], "SIX"));
+        assert_eq!((pair as *mut (u16, u64)).align_offset(align_of::<(u16, u64)>()), 0);
+        alloc.top().push(7);
+        let top = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| { alloc.alloc(8u8); }));
+        assert!(top.is_err());
+    }
+
+    #[test]
+    fn alloc_with_drop() {
+        use std::rc::Rc;
+
+        let counter = Rc::new(());
+        let mut alloc = BumpAlloc::new();
+        let mut alloc_ref = alloc.to_ref();
+        let kept = alloc_ref.alloc_with_drop(vec![counter.clone(), counter.clone()]);
+        kept.push(counter.clone());
+        let plain = alloc_ref.alloc_with_drop(7u8);
+        assert_eq!((Rc::strong_count(&counter), *plain), (4, 7));
+        alloc_ref.scope(|arena| {
+            arena.alloc_with_drop(counter.clone());
+            assert_eq!(Rc::strong_count(&counter), 5);
+        });
+        assert_eq!(Rc::strong_count(&counter), 4);
+        alloc.reset();
+        assert_eq!(Rc::strong_count(&counter), 1);
+        alloc.to_ref().alloc_with_drop(counter.clone());
+        drop(alloc);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn concat_join() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        assert_eq!(alloc.concat(&[b"ab", b"", b"c"]), b"abc");
+        assert_eq!(alloc.join(b", ", &[b"x", b"y", b"z"]), b"x, y, z");
+        assert_eq!(alloc.join(&[0u32], &[&[1, 2], &[3]]), [1, 2, 0, 3]);
+        assert!(alloc.join::<u8>(b"-", &[]).is_empty());
+        assert_eq!(alloc.data_size(), 3 + 7 + 2 + 4 * 4);
+    }
+
+    #[test]
+    fn collect_frozen() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let s1 = alloc.collect_frozen(b"hello".iter().map(u8::to_ascii_uppercase));
+        let s2 = alloc.collect_frozen((0..4u32).filter(|n| n % 2 == 1));
+        assert_eq!((&*s1, &*s2), (&b"HELLO"[..], &[1, 3][..]));
+    }
+
+    #[test]
+    fn freeze_group() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let tokens = alloc.freeze_group(b"let x = 42;".split(|&b| b == b' '));
+        assert_eq!(tokens, [&b"let"[..], b"x", b"=", b"42;"]);
+        assert!(tokens.iter().all(|t| alloc.contains(t.as_ptr())));
+        assert!(alloc.contains(tokens.as_ptr().cast()));
+        assert_eq!(alloc.freeze_group([]), [] as [&[u8]; 0]);
+    }
+
+    #[test]
+    fn dedup() {
+        let mut alloc = BumpAlloc::new().with_dedup();
+        let mut alloc_ref = alloc.to_ref();
+        let mut freeze = |bytes: &[u8]| {
+            let mut v = alloc_ref.top();
+            v.extend_from_slice(bytes);
+            v.freeze_dedup()
+        };
+        let s1 = freeze(b"record");
+        let s2 = freeze(b"other");
+        let s3 = freeze(b"record");
+        assert_eq!((s1.as_ptr(), s2, s3), (s3.as_ptr(), &b"other"[..], &b"record"[..]));
+        assert_eq!(alloc_ref.data_size(), 11);
+        alloc_ref.scope(|arena| {
+            let mut v = arena.top();
+            v.extend_from_slice(b"scoped");
+            v.freeze_dedup();
+        });
+        let mut v = alloc_ref.top();
+        v.extend_from_slice(b"scoped");
+        let s4 = v.freeze_dedup();
+        assert_eq!(alloc_ref.offset_of(s4), Some(11));
+        alloc.reset();
+        assert!(alloc.dedup.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn top_with_capacity() {
+        let mut alloc = BumpAlloc::from_boxed_buffer(vec![0; 64].into_boxed_slice());
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top_with_capacity(64).unwrap();
+        v1.extend_from_slice(&[1; 60]);
+        v1.freeze();
+        assert_eq!(alloc.top_with_capacity(5).err().map(|e| e.remaining()), Some(4));
+    }
+
+    #[test]
+    fn into_boxed_bytes() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc_ref = alloc.to_ref();
+        let mut v1 = alloc_ref.top();
+        v1.extend_from_slice(b"frozen ");
+        v1.freeze();
+        alloc_ref.top().extend_from_slice(b"and liquid");
+        assert_eq!(&*alloc.into_boxed_bytes(), b"frozen and liquid");
+    }
+
+    #[test]
+    fn page_source() {
+        /// Hands out (leaked) heap memory and keeps count of what's committed
+        struct Counting(std::rc::Rc<std::cell::Cell<usize>>);
+
+        unsafe impl PageSource for Counting {
+            fn reserve(&mut self, size: usize) -> Result<*mut u8, i32> {
+                Ok(Box::leak(vec![0u8; size].into_boxed_slice()).as_mut_ptr())
+            }
+            unsafe fn commit(&mut self, _ptr: *mut u8, len: usize) -> Result<(), i32> {
+                self.0.set(self.0.get() + len);
+                Ok(())
+            }
+            unsafe fn decommit(&mut self, _ptr: *mut u8, len: usize) {
+                self.0.set(self.0.get() - len);
+            }
+            unsafe fn release(&mut self, ptr: *mut u8, size: usize) {
+                drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, size)));
+            }
+        }
+
+        let committed = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut alloc = BumpAlloc::new_in(Counting(committed.clone()), 1 << 20);
+        let mut alloc = alloc.to_ref();
+
+        let s1: &mut [u8] = {
+            let mut v1 = alloc.top();
+            v1.extend_from_slice(&[1; 100]);
+            v1.freeze()
+        };
+        assert_eq!(committed.get(), 1 << 16);
+
+        let s2: &mut [u8] = {
+            let mut v2 = alloc.top();
+            v2.extend_from_slice(&[2; 1 << 16]);
+            v2.freeze()
+        };
+        assert_eq!(committed.get(), 1 << 17);
+
+        assert_eq!(s1, [1; 100]);
+        assert_eq!(s2, [2; 1 << 16]);
+    }
+
+    #[test]
+    #[should_panic(expected = "BumpAlloc exhausted")]
+    fn exhausted() {
+        let mut alloc = BumpAlloc::new_with_address_space(12);
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(&[0; 4000]);
+        v1.extend_from_slice(&[0; 100]);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{CapacityError, LiquidVecRef};
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const INVALID: u8 = 0xff;
+const HEX_DIGITS: [u8; 256] = digits(HEX, true);
+const BASE64_DIGITS: [u8; 256] = digits(BASE64, false);
+
+/// The value of each byte as a digit of `alphabet`, or `INVALID`, with uppercase letters too if `fold_case`
+const fn digits(alphabet: &[u8], fold_case: bool) -> [u8; 256] {
+    let mut table = [INVALID; 256];
+    let mut i = 0;
+    while i < alphabet.len() {
+        table[alphabet[i] as usize] = i as u8;
+        if fold_case {
+            table[alphabet[i].to_ascii_uppercase() as usize] = i as u8;
+        }
+        i += 1;
+    }
+    table
+}
+
+/// Hex or base64 input that can't be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    position: usize,
+}
+
+impl DecodeError {
+    /// The offset of the first byte that isn't a digit or isn't where it should be, the length of the input if it
+    /// stops in the middle of a group
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid hex or base64 input at byte {}", self.position)
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Binary to text encodings, for putting blobs in textual protocols
+impl<'alloc, 'data> LiquidVecRef<'alloc, 'data, u8> {
+    /// Appends `bytes` as lowercase hex digits, panicking when the arena is full
+    #[inline(always)]
+    pub fn extend_hex(&mut self, bytes: &[u8]) {
+        if let Err(e) = self.try_extend_hex(bytes) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `extend_hex`, but returns an error instead of panicking when the arena is full
+    pub fn try_extend_hex(&mut self, bytes: &[u8]) -> Result<(), CapacityError> {
+        self.try_reserve(bytes.len() * 2)?;
+        for &b in bytes {
+            self.push(HEX[b as usize >> 4]);
+            self.push(HEX[b as usize & 0xf]);
+        }
+        Ok(())
+    }
+
+    /// Appends `bytes` in standard base64 with padding (RFC 4648), panicking when the arena is full
+    #[inline(always)]
+    pub fn extend_base64(&mut self, bytes: &[u8]) {
+        if let Err(e) = self.try_extend_base64(bytes) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `extend_base64`, but returns an error instead of panicking when the arena is full
+    pub fn try_extend_base64(&mut self, bytes: &[u8]) -> Result<(), CapacityError> {
+        self.try_reserve(bytes.len().div_ceil(3) * 4)?;
+        for chunk in bytes.chunks(3) {
+            let group = chunk.iter().fold(0u32, |acc, &b| acc << 8 | b as u32) << (8 * (3 - chunk.len()));
+            for i in 0..4 {
+                let digit = BASE64[(group >> (18 - 6 * i)) as usize & 0x3f];
+                self.push(if i <= chunk.len() { digit } else { b'=' });
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes hex digits of either case and appends the bytes, panicking when the arena is full
+    ///
+    /// On error nothing is appended.
+    pub fn extend_from_hex(&mut self, hex: &[u8]) -> Result<(), DecodeError> {
+        if !hex.len().is_multiple_of(2) {
+            return Err(DecodeError { position: hex.len() });
+        }
+        let start = self.len();
+        self.reserve(hex.len() / 2);
+        for (i, pair) in hex.chunks_exact(2).enumerate() {
+            let (hi, lo) = (HEX_DIGITS[pair[0] as usize], HEX_DIGITS[pair[1] as usize]);
+            if hi == INVALID || lo == INVALID {
+                self.truncate(start);
+                return Err(DecodeError { position: 2 * i + (hi != INVALID) as usize });
+            }
+            self.push(hi << 4 | lo);
+        }
+        Ok(())
+    }
+
+    /// Decodes standard base64 and appends the bytes, panicking when the arena is full
+    ///
+    /// The padding at the end may be left out, but not whitespace. On error nothing is appended.
+    pub fn extend_from_base64(&mut self, base64: &[u8]) -> Result<(), DecodeError> {
+        let digits = base64.strip_suffix(b"==").or_else(|| base64.strip_suffix(b"=")).unwrap_or(base64);
+        if digits.len() != base64.len() && !base64.len().is_multiple_of(4) || digits.len() % 4 == 1 {
+            return Err(DecodeError { position: base64.len() });
+        }
+        let start = self.len();
+        self.reserve(digits.len() / 4 * 3 + 2);
+        for (i, chunk) in digits.chunks(4).enumerate() {
+            let mut group = 0;
+            for (j, &b) in chunk.iter().enumerate() {
+                let digit = BASE64_DIGITS[b as usize];
+                if digit == INVALID {
+                    self.truncate(start);
+                    return Err(DecodeError { position: 4 * i + j });
+                }
+                group = group << 6 | digit as u32;
+            }
+            group <<= 6 * (4 - chunk.len());
+            self.extend_from_slice(&group.to_be_bytes()[1..chunk.len()]);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::BumpAlloc;
+    use super::DecodeError;
+
+    #[test]
+    fn hex_base64() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v = alloc.top();
+        v.extend_hex(&[0x00, 0x7f, 0xab]);
+        v.push(b' ');
+        // Test vectors from RFC 4648
+        for s in ["", "f", "fo", "foo", "foob", "fooba", "foobar"] {
+            v.extend_base64(s.as_bytes());
+            v.push(b' ');
+        }
+        assert_eq!(*v, *b"007fab  Zg== Zm8= Zm9v Zm9vYg== Zm9vYmE= Zm9vYmFy ");
+        v.clear();
+
+        assert_eq!(v.extend_from_hex(b"007FaB"), Ok(()));
+        assert_eq!(v.extend_from_hex(b"0g"), Err(DecodeError { position: 1 }));
+        assert_eq!(v.extend_from_hex(b"abc"), Err(DecodeError { position: 3 }));
+        assert_eq!(*v, [0x00, 0x7f, 0xab]);
+        v.clear();
+
+        for s in ["Zg==", "Zm8", "Zm9vYmFy"] {
+            assert_eq!(v.extend_from_base64(s.as_bytes()), Ok(()));
+        }
+        assert_eq!(*v, *b"ffofoobar");
+        assert_eq!(v.extend_from_base64(b"Zm9v!A=="), Err(DecodeError { position: 4 }));
+        assert_eq!(v.extend_from_base64(b"Zm9vY"), Err(DecodeError { position: 5 }));
+        assert_eq!(v.extend_from_base64(b"Zg="), Err(DecodeError { position: 3 }));
+        assert_eq!(v.len(), 9);
+    }
+}
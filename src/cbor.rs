@@ -0,0 +1,219 @@
+use crate::LiquidVecRef;
+
+/// The major types of RFC 8949, in the top 3 bits of each item's first byte
+const UINT: u8 = 0;
+const NINT: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+/// The additional information for an indefinite length, and the break that ends it
+const INDEFINITE: u8 = 31;
+
+/// Writes CBOR (RFC 8949) into a byte vector in the arena
+///
+/// Each call writes one item, or the head of one for arrays, maps and tags, whose contents are the items written after
+/// it. Unlike `JsonWriter` it keeps no state, so nothing checks that containers get as many items as their head says
+/// or that indefinite ones are ended. Panics when the arena is full, like `LiquidVecRef::push`.
+#[repr(transparent)]
+pub struct CborWriter<'alloc, 'data> {
+    vec: LiquidVecRef<'alloc, 'data, u8>,
+}
+
+impl<'alloc, 'data> CborWriter<'alloc, 'data> {
+    /// Writes after whatever `vec` already holds
+    #[inline(always)]
+    pub fn new(vec: LiquidVecRef<'alloc, 'data, u8>) -> Self {
+        CborWriter { vec }
+    }
+
+    /// Freeze the bytes written, finishing the allocation
+    #[inline(always)]
+    pub fn freeze(self) -> &'data mut [u8] {
+        self.vec.freeze()
+    }
+
+    /// Unwraps the byte vector
+    #[inline(always)]
+    pub fn into_inner(self) -> LiquidVecRef<'alloc, 'data, u8> {
+        self.vec
+    }
+
+    /// The bytes written so far
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.vec
+    }
+
+    /// Writes an unsigned integer, in as few bytes as it fits
+    pub fn uint(&mut self, n: u64) {
+        self.head(UINT, n)
+    }
+
+    /// Writes a signed integer, in as few bytes as it fits
+    pub fn int(&mut self, n: i64) {
+        match n {
+            0.. => self.head(UINT, n as u64),
+            _ => self.head(NINT, !n as u64),
+        }
+    }
+
+    /// Writes a byte string
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.head(BYTES, bytes.len() as u64);
+        self.vec.extend_from_slice(bytes)
+    }
+
+    /// Writes a text string
+    pub fn text(&mut self, s: &str) {
+        self.head(TEXT, s.len() as u64);
+        self.vec.extend_from_slice(s.as_bytes())
+    }
+
+    /// Starts a byte string of unknown length, whose chunks are written with `bytes` until `end`
+    pub fn begin_bytes(&mut self) {
+        self.vec.put_u8(BYTES << 5 | INDEFINITE)
+    }
+
+    /// Starts a text string of unknown length, whose chunks are written with `text` until `end`
+    pub fn begin_text(&mut self) {
+        self.vec.put_u8(TEXT << 5 | INDEFINITE)
+    }
+
+    /// Starts an array of `len` items
+    pub fn array(&mut self, len: usize) {
+        self.head(ARRAY, len as u64)
+    }
+
+    /// Starts an array of unknown length, ended by `end`
+    pub fn begin_array(&mut self) {
+        self.vec.put_u8(ARRAY << 5 | INDEFINITE)
+    }
+
+    /// Starts a map of `len` pairs, each written as a key item followed by a value item
+    pub fn map(&mut self, len: usize) {
+        self.head(MAP, len as u64)
+    }
+
+    /// Starts a map of unknown length, ended by `end`
+    pub fn begin_map(&mut self) {
+        self.vec.put_u8(MAP << 5 | INDEFINITE)
+    }
+
+    /// Ends the innermost string, array or map of unknown length
+    pub fn end(&mut self) {
+        self.vec.put_u8(SIMPLE << 5 | INDEFINITE)
+    }
+
+    /// Tags the next item, such as 1 for an epoch timestamp
+    pub fn tag(&mut self, tag: u64) {
+        self.head(TAG, tag)
+    }
+
+    /// Writes `false` or `true`
+    pub fn bool(&mut self, value: bool) {
+        self.simple(20 + value as u8)
+    }
+
+    /// Writes `null`
+    pub fn null(&mut self) {
+        self.simple(22)
+    }
+
+    /// Writes `undefined`
+    pub fn undefined(&mut self) {
+        self.simple(23)
+    }
+
+    /// Writes a simple value, the ones below 20 are unassigned
+    ///
+    /// Panics for 24 to 31, which aren't well-formed as simple values.
+    pub fn simple(&mut self, value: u8) {
+        assert!(!(24..32).contains(&value), "CborWriter::simple: {value} is reserved");
+        self.head(SIMPLE, value as u64)
+    }
+
+    /// Writes a single precision float
+    pub fn f32(&mut self, value: f32) {
+        self.vec.put_u8(SIMPLE << 5 | 26);
+        self.vec.put_f32_be(value)
+    }
+
+    /// Writes a double precision float
+    pub fn f64(&mut self, value: f64) {
+        self.vec.put_u8(SIMPLE << 5 | 27);
+        self.vec.put_f64_be(value)
+    }
+
+    /// Writes the initial byte of an item, followed by `n` if it doesn't fit in it
+    fn head(&mut self, major: u8, n: u64) {
+        let major = major << 5;
+        match n {
+            0..24 => self.vec.put_u8(major | n as u8),
+            24..0x100 => self.vec.extend_from_slice(&[major | 24, n as u8]),
+            0x100..0x1_0000 => {
+                self.vec.put_u8(major | 25);
+                self.vec.put_u16_be(n as u16)
+            }
+            0x1_0000..0x1_0000_0000 => {
+                self.vec.put_u8(major | 26);
+                self.vec.put_u32_be(n as u32)
+            }
+            _ => {
+                self.vec.put_u8(major | 27);
+                self.vec.put_u64_be(n)
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::BumpAlloc;
+    use super::CborWriter;
+
+    #[test]
+    fn cbor() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        // Examples from appendix A of RFC 8949
+        let mut encode = |f: &dyn Fn(&mut CborWriter)| {
+            let mut w = CborWriter::new(alloc.top());
+            f(&mut w);
+            w.freeze().to_vec()
+        };
+        assert_eq!(encode(&|w| w.uint(23)), [0x17]);
+        assert_eq!(encode(&|w| w.uint(100)), [0x18, 0x64]);
+        assert_eq!(encode(&|w| w.uint(1000)), [0x19, 0x03, 0xe8]);
+        assert_eq!(encode(&|w| w.uint(1000000)), [0x1a, 0x00, 0x0f, 0x42, 0x40]);
+        assert_eq!(encode(&|w| w.uint(1000000000000)), [0x1b, 0, 0, 0, 0xe8, 0xd4, 0xa5, 0x10, 0]);
+        assert_eq!(encode(&|w| w.int(-1000)), [0x39, 0x03, 0xe7]);
+        assert_eq!(encode(&|w| w.int(i64::MIN)), [0x3b, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(encode(&|w| w.f64(1.1)), [0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]);
+        assert_eq!(encode(&|w| w.f32(100000.0)), [0xfa, 0x47, 0xc3, 0x50, 0x00]);
+        assert_eq!(encode(&|w| { w.bool(true); w.null(); w.undefined(); w.simple(255) }), [0xf5, 0xf6, 0xf7, 0xf8, 0xff]);
+        assert_eq!(encode(&|w| { w.tag(1); w.uint(1363896240) }), [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]);
+        assert_eq!(encode(&|w| w.bytes(&[1, 2, 3, 4])), [0x44, 1, 2, 3, 4]);
+        assert_eq!(encode(&|w| w.text("\u{6c34}")), [0x63, 0xe6, 0xb0, 0xb4]);
+        assert_eq!(encode(&|w| { w.map(1); w.text("a"); w.array(2); w.uint(2); w.uint(3) }), [0xa1, 0x61, 0x61, 0x82, 2, 3]);
+        assert_eq!(
+            encode(&|w| { w.begin_map(); w.text("a"); w.begin_array(); w.uint(1); w.end(); w.end() }),
+            [0xbf, 0x61, 0x61, 0x9f, 0x01, 0xff, 0xff],
+        );
+        assert_eq!(
+            encode(&|w| { w.begin_text(); w.text("strea"); w.text("ming"); w.end() }),
+            *b"\x7f\x65strea\x64ming\xff",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "24 is reserved")]
+    fn cbor_reserved_simple() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        CborWriter::new(alloc.top()).simple(24);
+    }
+}
@@ -0,0 +1,233 @@
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use crate::BumpAllocRef;
+
+/// Minimum degree: every node but the root has between `B - 1` and `2B - 1` keys
+const B: usize = 6;
+const CAP: usize = 2 * B - 1;
+/// Deeper than any tree of minimum degree `B` that fits in memory
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Copy)]
+struct Node<K: Copy, V: Copy> {
+    len: usize,
+    keys: [MaybeUninit<K>; CAP],
+    vals: [MaybeUninit<V>; CAP],
+    /// All null in a leaf
+    children: [*mut Node<K, V>; CAP + 1],
+}
+
+impl<K: Copy + Ord, V: Copy> Node<K, V> {
+    const EMPTY: Self = Node {
+        len: 0,
+        keys: [MaybeUninit::uninit(); CAP],
+        vals: [MaybeUninit::uninit(); CAP],
+        children: [core::ptr::null_mut(); CAP + 1],
+    };
+
+    fn is_leaf(&self) -> bool {
+        self.children[0].is_null()
+    }
+
+    fn key(&self, i: usize) -> &K {
+        unsafe { self.keys[i].assume_init_ref() }
+    }
+
+    fn search<Q: Ord + ?Sized>(&self, key: &Q) -> Result<usize, usize> where K: Borrow<Q> {
+        self.keys[..self.len].binary_search_by(|k| unsafe { k.assume_init_ref() }.borrow().cmp(key))
+    }
+
+    /// Puts a key and value at `i`, shifting the ones after it right
+    fn insert_at(&mut self, i: usize, key: K, value: V) {
+        self.keys.copy_within(i..self.len, i + 1);
+        self.vals.copy_within(i..self.len, i + 1);
+        self.keys[i].write(key);
+        self.vals[i].write(value);
+        self.len += 1;
+    }
+}
+
+/// A B-tree map with its nodes in the arena, keeping its keys sorted
+///
+/// Nodes hold up to 11 entries, so lookups touch few cache lines, and they're allocated next to each other as the
+/// tree is built.
+pub struct ArenaBTree<'data, K: Copy, V: Copy> {
+    root: *mut Node<K, V>,
+    len: usize,
+    _data: PhantomData<&'data mut Node<K, V>>,
+}
+
+impl<'data, K: Copy + Ord, V: Copy> ArenaBTree<'data, K, V> {
+    /// An empty tree, which allocates nothing until the first insert
+    pub fn new() -> Self {
+        ArenaBTree { root: core::ptr::null_mut(), len: 0, _data: PhantomData }
+    }
+
+    /// Sets the value for `key`, returning the one it replaces
+    pub fn insert(&mut self, arena: &mut BumpAllocRef<'data>, key: K, value: V) -> Option<V> {
+        unsafe {
+            if self.root.is_null() {
+                self.root = Self::alloc_node(arena);
+            }
+            if (*self.root).len == CAP {
+                let root = Self::alloc_node(arena);
+                (*root).children[0] = self.root;
+                Self::split_child(arena, &mut *root, 0);
+                self.root = root;
+            }
+            let mut node = &mut *self.root;
+            loop {
+                let mut i = match node.search(&key) {
+                    Ok(i) => return Some(core::mem::replace(node.vals[i].assume_init_mut(), value)),
+                    Err(i) => i,
+                };
+                if node.is_leaf() {
+                    node.insert_at(i, key, value);
+                    self.len += 1;
+                    return None;
+                }
+                if (*node.children[i]).len == CAP {
+                    Self::split_child(arena, node, i);
+                    match key.cmp(node.key(i)) {
+                        Ordering::Equal => return Some(core::mem::replace(node.vals[i].assume_init_mut(), value)),
+                        Ordering::Greater => i += 1,
+                        Ordering::Less => {}
+                    }
+                }
+                node = &mut *node.children[i];
+            }
+        }
+    }
+
+    /// The value for `key`
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V> where K: Borrow<Q> {
+        let mut node = unsafe { self.root.as_ref()? };
+        loop {
+            match node.search(key) {
+                Ok(i) => return Some(unsafe { node.vals[i].assume_init_ref() }),
+                Err(_) if node.is_leaf() => return None,
+                Err(i) => node = unsafe { &*node.children[i] },
+            }
+        }
+    }
+
+    /// Whether there's a value for `key`
+    pub fn contains_key<Q: Ord + ?Sized>(&self, key: &Q) -> bool where K: Borrow<Q> {
+        self.get(key).is_some()
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The entries in key order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let mut iter = Iter { stack: [(core::ptr::null(), 0); MAX_DEPTH], depth: 0, _tree: PhantomData };
+        iter.descend(self.root);
+        iter
+    }
+
+    fn alloc_node(arena: &mut BumpAllocRef<'data>) -> *mut Node<K, V> {
+        let mut v = arena.empty_top::<Node<K, V>>("ArenaBTree::insert");
+        v.push(Node::EMPTY);
+        let node = v.freeze().as_mut_ptr();
+        unsafe { (*arena.ptr).unstamp_last() };
+        node
+    }
+
+    /// Splits the full child `i` of `parent` in two around its middle key, which moves up into `parent`
+    unsafe fn split_child(arena: &mut BumpAllocRef<'data>, parent: &mut Node<K, V>, i: usize) {
+        let right = unsafe { &mut *Self::alloc_node(arena) };
+        let left = unsafe { &mut *parent.children[i] };
+        right.len = B - 1;
+        right.keys[..B - 1].copy_from_slice(&left.keys[B..]);
+        right.vals[..B - 1].copy_from_slice(&left.vals[B..]);
+        if !left.is_leaf() {
+            right.children[..B].copy_from_slice(&left.children[B..]);
+        }
+        left.len = B - 1;
+        parent.children.copy_within(i + 1..parent.len + 1, i + 2);
+        parent.children[i + 1] = right;
+        parent.insert_at(i, *left.key(B - 1), unsafe { left.vals[B - 1].assume_init() });
+    }
+}
+
+impl<'data, K: Copy + Ord, V: Copy> Default for ArenaBTree<'data, K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// In-order traversal, keeping the path from the root and the next key to visit in each node of it
+struct Iter<'a, K: Copy, V: Copy> {
+    stack: [(*const Node<K, V>, usize); MAX_DEPTH],
+    depth: usize,
+    _tree: PhantomData<&'a Node<K, V>>,
+}
+
+impl<'a, K: Copy + Ord, V: Copy> Iter<'a, K, V> {
+    /// Pushes the leftmost path down from `node`
+    fn descend(&mut self, mut node: *const Node<K, V>) {
+        while !node.is_null() {
+            self.stack[self.depth] = (node, 0);
+            self.depth += 1;
+            node = unsafe { (*node).children[0] };
+        }
+    }
+}
+
+impl<'a, K: Copy + Ord, V: Copy> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, i) = *self.stack[..self.depth].last()?;
+            let node: &'a Node<K, V> = unsafe { &*node };
+            if i == node.len {
+                self.depth -= 1;
+                continue;
+            }
+            self.stack[self.depth - 1].1 = i + 1;
+            self.descend(node.children[i + 1]);
+            return Some((node.key(i), unsafe { node.vals[i].assume_init_ref() }));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::BumpAlloc;
+    use super::ArenaBTree;
+
+    #[test]
+    fn btree() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut tree = ArenaBTree::new();
+        assert_eq!(tree.iter().next(), None);
+        for i in 0..1000u32 {
+            let key = (i * 7919) % 1000;
+            assert_eq!(tree.insert(&mut alloc, key, i), None);
+        }
+        let at_500 = (0..1000).find(|i| i * 7919 % 1000 == 500);
+        assert_eq!(tree.insert(&mut alloc, 500, 0), at_500);
+        assert_eq!((tree.len(), tree.get(&500), tree.get(&1000)), (1000, Some(&0), None));
+        assert!(tree.iter().map(|(k, _)| *k).eq(0..1000));
+
+        let mut names = ArenaBTree::new();
+        for name in ["pear", "apple", "fig"] {
+            let key = alloc.alloc_str(name);
+            names.insert(&mut alloc, &*key, name.len());
+        }
+        assert_eq!(names.get("fig"), Some(&3));
+        assert!(names.iter().map(|(k, _)| *k).eq(["apple", "fig", "pear"]));
+    }
+}
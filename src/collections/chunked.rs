@@ -0,0 +1,115 @@
+use core::marker::PhantomData;
+use crate::BumpAllocRef;
+use super::alloc_uninit;
+
+/// Elements in the first chunk, each further chunk is twice as big as the one before
+const FIRST: usize = 8;
+
+/// A vector that grows by allocating chunks in the arena, so it can keep growing while other allocations happen
+///
+/// Unlike a `LiquidVecRef`, it doesn't hold on to the top vector between pushes, which suits lists built up next to
+/// the data they index, like the `ArenaSpan`s of records being parsed. Elements never move once pushed.
+pub struct ChunkedVec<'data, T: Copy> {
+    chunks: [*mut T; usize::BITS as usize - 3],
+    len: usize,
+    _data: PhantomData<&'data mut T>,
+}
+
+impl<'data, T: Copy> ChunkedVec<'data, T> {
+    /// An empty vector, which allocates nothing until the first push
+    pub fn new() -> Self {
+        ChunkedVec { chunks: [core::ptr::null_mut(); usize::BITS as usize - 3], len: 0, _data: PhantomData }
+    }
+
+    /// Appends `item`, allocating a new chunk when the last one is full
+    pub fn push(&mut self, arena: &mut BumpAllocRef<'data>, item: T) {
+        let (chunk, offset) = Self::locate(self.len);
+        if offset == 0 && self.chunks[chunk].is_null() {
+            let slots = alloc_uninit::<T>(arena, FIRST << chunk, "ChunkedVec::push");
+            self.chunks[chunk] = slots.as_mut_ptr().cast();
+        }
+        unsafe { self.chunks[chunk].add(offset).write(item) };
+        self.len += 1;
+    }
+
+    /// Removes the last element and returns it; its slot is reused by the next push
+    pub fn pop(&mut self) -> Option<T> {
+        self.len = self.len.checked_sub(1)?;
+        let (chunk, offset) = Self::locate(self.len);
+        Some(unsafe { self.chunks[chunk].add(offset).read() })
+    }
+
+    /// The element at `index`
+    pub fn get(&self, index: usize) -> Option<&T> {
+        (index < self.len).then(|| {
+            let (chunk, offset) = Self::locate(index);
+            unsafe { &*self.chunks[chunk].add(offset) }
+        })
+    }
+
+    /// The element at `index`, mutably
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        (index < self.len).then(|| {
+            let (chunk, offset) = Self::locate(index);
+            unsafe { &mut *self.chunks[chunk].add(offset) }
+        })
+    }
+
+    /// The number of elements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The elements in order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator + '_ {
+        (0..self.len).map(|i| {
+            let (chunk, offset) = Self::locate(i);
+            unsafe { &*self.chunks[chunk].add(offset) }
+        })
+    }
+
+    /// The chunk holding element `index`, and its position there
+    fn locate(index: usize) -> (usize, usize) {
+        let chunk = (index / FIRST + 1).ilog2() as usize;
+        (chunk, index - FIRST * ((1 << chunk) - 1))
+    }
+}
+
+impl<'data, T: Copy> Default for ChunkedVec<'data, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{ArenaSpan, BumpAlloc};
+    use super::ChunkedVec;
+
+    #[test]
+    fn chunked() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut spans = ChunkedVec::new();
+        for i in 0..100 {
+            let mut v = alloc.top();
+            v.extend_from_slice(&[i as u8; 3]);
+            let span = v.freeze_span();
+            spans.push(&mut alloc, span);
+        }
+        assert_eq!(ChunkedVec::<u8>::locate(7), (0, 7));
+        assert_eq!(ChunkedVec::<u8>::locate(8), (1, 0));
+        assert_eq!(ChunkedVec::<u8>::locate(24), (2, 0));
+        assert_eq!(spans.len(), 100);
+        assert_eq!(spans.get(99).map(ArenaSpan::len), Some(3));
+        assert!(spans.iter().zip(spans.iter().skip(1)).all(|(a, b)| a.offset() < b.offset()));
+        *spans.get_mut(0).unwrap() = ArenaSpan::new(1, 2);
+        assert_eq!(spans.pop().map(|s| s.len()), Some(3));
+        assert_eq!((spans.len(), spans.get(99), spans.iter().next()), (99, None, Some(&ArenaSpan::new(1, 2))));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::BumpAllocRef;
+use super::{ArenaMap, ChunkedVec};
+
+/// A string interned by an `Interner`, numbered in the order the strings were first seen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// The position of the string among those interned, starting at 0
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Deduplicates strings into the arena, handing out a small `Symbol` for each distinct one
+///
+/// Every string is copied into the arena once, and both the lookup table and the list of strings live there too.
+pub struct Interner<'data> {
+    symbols: ArenaMap<'data, &'data str, Symbol>,
+    strings: ChunkedVec<'data, &'data str>,
+}
+
+impl<'data> Interner<'data> {
+    /// An empty interner, which allocates nothing until the first string
+    pub fn new() -> Self {
+        Interner { symbols: ArenaMap::new(), strings: ChunkedVec::new() }
+    }
+
+    /// The symbol for `s`, copying it into the arena if it hasn't been seen before
+    ///
+    /// Panics if the top vector isn't empty, the arena is exhausted, or there are more than `u32::MAX` strings.
+    pub fn intern(&mut self, arena: &mut BumpAllocRef<'data>, s: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(s) {
+            return symbol;
+        }
+        let symbol = Symbol(u32::try_from(self.strings.len()).expect("Interner::intern: too many strings"));
+        let s: &'data str = arena.alloc_str(s);
+        self.strings.push(arena, s);
+        self.symbols.insert(arena, s, symbol);
+        symbol
+    }
+
+    /// The symbol for `s`, if it has been interned
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.symbols.get(s).copied()
+    }
+
+    /// The string of a symbol from this interner
+    ///
+    /// Panics if the symbol comes from an interner with fewer strings.
+    pub fn resolve(&self, symbol: Symbol) -> &'data str {
+        self.strings.get(symbol.index()).copied().expect("Interner::resolve: the symbol is from another interner")
+    }
+
+    /// The number of distinct strings
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Whether no string has been interned
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// The interned strings with their symbols, in the order they were first seen
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &'data str)> + '_ {
+        self.strings.iter().enumerate().map(|(i, &s)| (Symbol(i as u32), s))
+    }
+}
+
+impl<'data> Default for Interner<'data> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::BumpAlloc;
+    use super::Interner;
+
+    #[test]
+    fn interner() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut interner = Interner::new();
+        let words = "let x = x + y ; let y = x".split(' ');
+        let symbols: Vec<_> = words.clone().map(|w| interner.intern(&mut alloc, w)).collect();
+        assert_eq!(symbols[1], symbols[3]);
+        assert_eq!(symbols[0].index(), 0);
+        assert_eq!(interner.len(), 6);
+        assert!(symbols.iter().map(|&s| interner.resolve(s)).eq(words));
+        assert_eq!(interner.get("y").map(|s| s.index()), Some(4));
+        assert_eq!(interner.get("z"), None);
+        assert!(interner.iter().map(|(_, s)| s).eq(["let", "x", "=", "+", "y", ";"]));
+    }
+}
@@ -0,0 +1,161 @@
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+use crate::BumpAllocRef;
+use super::alloc_uninit;
+
+/// FNV-1a, a small hash that's quick on the short keys typical of parsers
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn hash<Q: Hash + ?Sized>(key: &Q) -> u64 {
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A hash map with its table in the arena, using open addressing with linear probing
+///
+/// String keys can be put in the arena too with `BumpAllocRef::alloc_str`, and looked up by `&str`. The table doubles
+/// when it's three quarters full, leaving the old one behind in the arena.
+pub struct ArenaMap<'data, K: Copy, V: Copy> {
+    slots: &'data mut [Option<(K, V)>],
+    len: usize,
+}
+
+impl<'data, K: Copy + Hash + Eq, V: Copy> ArenaMap<'data, K, V> {
+    /// An empty map, which allocates nothing until the first insert
+    pub fn new() -> Self {
+        ArenaMap { slots: &mut [], len: 0 }
+    }
+
+    /// An empty map with room for `n` entries before it grows
+    pub fn with_capacity(arena: &mut BumpAllocRef<'data>, n: usize) -> Self {
+        let mut map = Self::new();
+        map.grow(arena, (n * 4 / 3 + 1).next_power_of_two().max(8));
+        map
+    }
+
+    /// Sets the value for `key`, returning the one it replaces
+    pub fn insert(&mut self, arena: &mut BumpAllocRef<'data>, key: K, value: V) -> Option<V> {
+        if (self.len + 1) * 4 > self.slots.len() * 3 {
+            self.grow(arena, (self.slots.len() * 2).max(8));
+        }
+        match self.find(&key) {
+            Ok(i) => self.slots[i].replace((key, value)).map(|(_, old)| old),
+            Err(i) => {
+                self.slots[i] = Some((key, value));
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    /// The value for `key`
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V> where K: Borrow<Q> {
+        let i = self.find(key).ok()?;
+        self.slots[i].as_ref().map(|(_, v)| v)
+    }
+
+    /// The value for `key`, mutably
+    pub fn get_mut<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V> where K: Borrow<Q> {
+        let i = self.find(key).ok()?;
+        self.slots[i].as_mut().map(|(_, v)| v)
+    }
+
+    /// Whether there's a value for `key`
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool where K: Borrow<Q> {
+        self.find(key).is_ok()
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The entries, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.slots.iter().flatten().map(|(k, v)| (k, v))
+    }
+
+    /// The slot holding `key`, or else the empty slot where it would go
+    fn find<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Result<usize, usize> where K: Borrow<Q> {
+        if self.slots.is_empty() {
+            return Err(0);
+        }
+        let mask = self.slots.len() - 1;
+        let mut i = hash(key) as usize & mask;
+        loop {
+            match &self.slots[i] {
+                None => return Err(i),
+                Some((k, _)) if k.borrow() == key => return Ok(i),
+                Some(_) => i = (i + 1) & mask,
+            }
+        }
+    }
+
+    fn grow(&mut self, arena: &mut BumpAllocRef<'data>, capacity: usize) {
+        let slots = alloc_uninit::<Option<(K, V)>>(arena, capacity, "ArenaMap::insert");
+        for slot in slots.iter_mut() {
+            slot.write(None);
+        }
+        let slots = unsafe { &mut *(slots as *mut [_] as *mut [Option<(K, V)>]) };
+        let old = core::mem::replace(&mut self.slots, slots);
+        for &(k, v) in old.iter().flatten() {
+            if let Err(i) = self.find(&k) {
+                self.slots[i] = Some((k, v));
+            }
+        }
+    }
+}
+
+impl<'data, K: Copy + Hash + Eq, V: Copy> Default for ArenaMap<'data, K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::BumpAlloc;
+    use super::ArenaMap;
+
+    #[test]
+    fn map() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut map = ArenaMap::new();
+        for i in 0..100u32 {
+            let key = alloc.alloc_str(&format!("key{i}"));
+            assert_eq!(map.insert(&mut alloc, &*key, i), None);
+        }
+        assert_eq!(map.insert(&mut alloc, "key7", 700), Some(7));
+        *map.get_mut("key8").unwrap() += 800;
+        assert_eq!((map.get("key7"), map.get("key8"), map.get("key100")), (Some(&700), Some(&808), None));
+        assert_eq!((map.len(), map.iter().count()), (100, 100));
+        assert!(map.contains_key("key99"));
+        assert!(alloc.contains(map.get("key0").unwrap() as *const u32 as *const u8));
+
+        let mut sized: ArenaMap<u64, u8> = ArenaMap::with_capacity(&mut alloc, 6);
+        let before = alloc.data_size();
+        for i in 0..6 {
+            sized.insert(&mut alloc, i, i as u8);
+        }
+        assert_eq!(alloc.data_size(), before);
+    }
+}
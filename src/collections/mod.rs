@@ -0,0 +1,27 @@
+//! Index structures whose storage lives in a `BumpAlloc`, for building lookups alongside the data they point into
+//!
+//! They hold `Copy` keys and values, take the `BumpAllocRef` to allocate from on every call that may grow them, and
+//! need the top vector to be empty at that point. Space they grow out of isn't reused, as with any arena.
+
+use core::mem::MaybeUninit;
+use crate::BumpAllocRef;
+
+mod map;
+mod btree;
+mod chunked;
+mod interner;
+
+pub use map::ArenaMap;
+pub use btree::ArenaBTree;
+pub use chunked::ChunkedVec;
+pub use interner::{Interner, Symbol};
+
+/// Allocates room for `n` elements of `T` without initializing them
+fn alloc_uninit<'data, T: Copy>(arena: &mut BumpAllocRef<'data>, n: usize, op: &str) -> &'data mut [MaybeUninit<T>] {
+    let mut v = arena.empty_top::<MaybeUninit<T>>(op);
+    v.reserve(n);
+    unsafe { v.set_len(n) };
+    let slots = v.freeze();
+    unsafe { (*arena.ptr).unstamp_last() };
+    slots
+}
@@ -0,0 +1,106 @@
+use crate::BumpAllocRef;
+
+/// A dynamically sized `#[repr(C)]` type made of a `Header` followed by a trailing `[Elem]`, see `BumpAllocRef::alloc_dst`
+///
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use freeze::{BumpAlloc, SliceDst};
+///
+/// #[repr(C)]
+/// struct Record {
+///     id: u32,
+///     values: [u16],
+/// }
+///
+/// unsafe impl SliceDst for Record {
+///     type Header = u32;
+///     type Elem = u16;
+///
+///     fn cast(ptr: *mut [u16]) -> *mut Self {
+///         ptr as *mut Self
+///     }
+/// }
+///
+/// let mut alloc = BumpAlloc::new();
+/// let mut alloc = alloc.to_ref();
+/// let record: &mut Record = alloc.alloc_dst(7, &[1, 2, 3]);
+/// assert_eq!((record.id, &record.values), (7, &[1, 2, 3][..]));
+/// # }
+/// ```
+///
+/// # Safety
+/// The type must be `#[repr(C)]` with `Header`'s fields first and a `[Elem]` as its last field, so that it's laid out
+/// as a `Header` followed by the slice; `cast` must only change the pointer's type, keeping its address and length.
+pub unsafe trait SliceDst {
+    /// The sized part before the trailing slice; a struct of several fields must be `#[repr(C)]` in the same order
+    type Header: Copy;
+    /// The element type of the trailing slice
+    type Elem: Copy;
+
+    /// Turns a pointer to the trailing elements, starting at the address of the whole value, into a pointer to `Self`
+    fn cast(ptr: *mut [Self::Elem]) -> *mut Self;
+}
+
+impl<'data> BumpAllocRef<'data> {
+    /// Allocates a dynamically sized value in one step, writing its header and copying its trailing slice
+    ///
+    /// Panics if the top vector isn't empty or the arena is exhausted, like the other `alloc_` methods.
+    pub fn alloc_dst<D: SliceDst + ?Sized>(&mut self, header: D::Header, tail: &[D::Elem]) -> &'data mut D {
+        let align = align_of::<D::Header>().max(align_of::<D::Elem>());
+        let offset = size_of::<D::Header>().next_multiple_of(align_of::<D::Elem>());
+        let size = (offset + size_of_val(tail)).next_multiple_of(align);
+        let mut v = self.empty_top::<u8>("BumpAllocRef::alloc_dst");
+        v.alloc.align_top(align);
+        v.extend_zeroed(size);
+        unsafe {
+            let base = v.as_mut_ptr();
+            base.cast::<D::Header>().write(header);
+            base.add(offset).cast::<D::Elem>().copy_from_nonoverlapping(tail.as_ptr(), tail.len());
+            let ptr = D::cast(core::ptr::slice_from_raw_parts_mut(v.freeze().as_mut_ptr().cast(), tail.len()));
+            debug_assert_eq!(size_of_val(&*ptr), size, "SliceDst::cast: the layout is not a header and a trailing slice");
+            &mut *ptr
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::BumpAlloc;
+    use super::SliceDst;
+
+    #[repr(C)]
+    struct Packet {
+        header: Header,
+        payload: [u8],
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[repr(C)]
+    struct Header {
+        kind: u8,
+        seq: u64,
+    }
+
+    unsafe impl SliceDst for Packet {
+        type Header = Header;
+        type Elem = u8;
+
+        fn cast(ptr: *mut [u8]) -> *mut Self {
+            ptr as *mut Self
+        }
+    }
+
+    #[test]
+    fn alloc_dst() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        alloc.alloc(1u8);
+        let p1: &mut Packet = alloc.alloc_dst(Header { kind: 2, seq: 3 }, b"abc");
+        let p2: &mut Packet = alloc.alloc_dst(Header { kind: 4, seq: 5 }, b"");
+        p1.payload[0] = b'A';
+        assert_eq!((p1.header, &p1.payload), (Header { kind: 2, seq: 3 }, &b"Abc"[..]));
+        assert_eq!((p2.header, &p2.payload), (Header { kind: 4, seq: 5 }, &b""[..]));
+        assert_eq!((p1 as *mut Packet).cast::<u8>().align_offset(8), 0);
+        assert_eq!(alloc.data_size(), 8 + 24 + 16);
+    }
+}
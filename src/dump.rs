@@ -0,0 +1,83 @@
+use core::fmt::Write;
+use crate::{BumpAlloc, PageSource};
+
+/// Bytes shown per line of the hexdump
+const LINE: usize = 16;
+
+impl<S: ?Sized + PageSource> BumpAlloc<S> {
+    /// An annotated hexdump of the arena: the frozen allocations, the top vector, and how much is left
+    ///
+    /// With a registry (see `with_registry`) every frozen allocation is shown on its own, along with the canaries and
+    /// padding between them; without one the frozen part is a single region.
+    pub fn dump(&self) -> String {
+        let frozen = unsafe { self.top_base.offset_from(self.data_base) as usize };
+        let mut out = String::new();
+        let _ = writeln!(out, "BumpAlloc: {} bytes allocated, {} frozen in {} freezes, {} of {} bytes free",
+                         self.top_end(), frozen, self.freezes, self.remaining(), self.address_space);
+        let mut at = 0;
+        for record in self.registry.iter().flatten() {
+            if record.offset > at {
+                self.dump_region(&mut out, "gap", at, record.offset);
+            }
+            let label = match record.tag {
+                Some(tag) => format!("frozen, tag {tag}"),
+                None => "frozen".to_string(),
+            };
+            self.dump_region(&mut out, &label, record.offset, record.offset + record.len);
+            at = record.offset + record.len;
+        }
+        if frozen > at {
+            self.dump_region(&mut out, if self.registry.is_some() { "gap" } else { "frozen" }, at, frozen);
+        }
+        self.dump_region(&mut out, "top", frozen, self.top_end());
+        let _ = writeln!(out, "free [{:#x}, {:#x})", self.top_end(), self.address_space);
+        out
+    }
+
+    fn dump_region(&self, out: &mut String, label: &str, start: usize, end: usize) {
+        let _ = writeln!(out, "{label} [{start:#x}, {end:#x}) {} bytes", end - start);
+        for line in (start..end).step_by(LINE) {
+            let bytes: Vec<u8> = (line..end.min(line + LINE)).map(|i| unsafe { self.data_base.add(i).read() }).collect();
+            let _ = write!(out, "  {line:08x} ");
+            for i in 0..LINE {
+                match bytes.get(i) {
+                    Some(b) => { let _ = write!(out, " {b:02x}"); }
+                    None => out.push_str("   "),
+                }
+            }
+            let text: String = bytes.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+            let _ = writeln!(out, "  |{text}|");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BumpAlloc;
+
+    #[test]
+    fn dump() {
+        let mut alloc = BumpAlloc::new_with_address_space(12).with_registry();
+        let mut alloc_ref = alloc.to_ref();
+        let mut v1 = alloc_ref.top();
+        v1.extend_from_slice(b"hello");
+        v1.freeze_tagged(7);
+        let mut v2 = alloc_ref.top_of::<u32>();
+        v2.extend_one(0x01020304);
+        v2.freeze();
+        alloc_ref.top().extend_from_slice(b"0123456789abcdefXY");
+        assert_eq!(alloc.dump(), "\
+BumpAlloc: 30 bytes allocated, 12 frozen in 2 freezes, 4066 of 4096 bytes free
+frozen, tag 7 [0x0, 0x5) 5 bytes
+  00000000  68 65 6c 6c 6f                                   |hello|
+gap [0x5, 0x8) 3 bytes
+  00000005  00 00 00                                         |...|
+frozen [0x8, 0xc) 4 bytes
+  00000008  04 03 02 01                                      |....|
+top [0xc, 0x1e) 18 bytes
+  0000000c  30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66  |0123456789abcdef|
+  0000001c  58 59                                            |XY|
+free [0x1e, 0x1000)
+");
+    }
+}
@@ -0,0 +1,273 @@
+use crate::LiquidVecRef;
+
+/// Generates `put_*` methods appending a number in the given byte order
+macro_rules! put {
+    ($($name:ident: $t:ty => $to_bytes:ident;)*) => {
+        $(
+            #[doc = concat!("Appends a `", stringify!($t), "` with `", stringify!($to_bytes), "`, panicking when the arena is full")]
+            #[inline(always)]
+            pub fn $name(&mut self, n: $t) {
+                self.extend_from_slice(&n.$to_bytes())
+            }
+        )*
+    };
+}
+
+/// The size and byte order of the length prefix written by `LiquidVecRef::begin_frame`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixWidth {
+    U8,
+    U16Le,
+    U16Be,
+    U32Le,
+    U32Be,
+    U64Le,
+    U64Be,
+}
+
+impl PrefixWidth {
+    /// The number of bytes the prefix takes
+    pub const fn size(self) -> usize {
+        match self {
+            PrefixWidth::U8 => 1,
+            PrefixWidth::U16Le | PrefixWidth::U16Be => 2,
+            PrefixWidth::U32Le | PrefixWidth::U32Be => 4,
+            PrefixWidth::U64Le | PrefixWidth::U64Be => 8,
+        }
+    }
+
+    /// The largest body length the prefix can hold
+    pub const fn max_len(self) -> u64 {
+        match self.size() {
+            8 => u64::MAX,
+            size => (1 << (size * 8)) - 1,
+        }
+    }
+}
+
+/// An open length-prefixed frame in a top vector, closed by `LiquidVecRef::end_frame`
+///
+/// It only records where the prefix is, so frames nest, and the vector is free to grow while they're open.
+#[must_use = "the length prefix is only written by end_frame"]
+#[derive(Debug)]
+pub struct Frame {
+    start: usize,
+    width: PrefixWidth,
+}
+
+/// Bytes reserved in a top vector by `LiquidVecRef::reserve_patch`, to be filled once their value is known
+///
+/// Like a `Frame` it's just a position in the vector, which can keep growing until the patch is written.
+#[must_use = "the reserved bytes stay zero unless the patch is written"]
+#[derive(Debug)]
+pub struct Patch {
+    start: usize,
+    len: usize,
+}
+
+impl Patch {
+    /// The number of bytes reserved
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no bytes were reserved
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Fills the reserved bytes of the vector the patch was made in
+    ///
+    /// Panics if `bytes` isn't exactly as long as the reservation, or if the vector was truncated past it.
+    pub fn write(self, vec: &mut LiquidVecRef<'_, '_, u8>, bytes: &[u8]) {
+        assert_eq!(bytes.len(), self.len, "Patch::write: the patch reserved {} bytes, got {}", self.len, bytes.len());
+        assert!(self.start + self.len <= vec.len(), "Patch::write: the vector was truncated past the patch");
+        vec[self.start..self.start + self.len].copy_from_slice(bytes);
+    }
+}
+
+/// Helpers for binary encoders writing into a byte vector
+impl <'alloc, 'data> LiquidVecRef<'alloc, 'data, u8> {
+    /// Appends a byte, panicking when the arena is full
+    #[inline(always)]
+    pub fn put_u8(&mut self, n: u8) {
+        self.push(n)
+    }
+
+    /// Appends a signed byte, panicking when the arena is full
+    #[inline(always)]
+    pub fn put_i8(&mut self, n: i8) {
+        self.push(n as u8)
+    }
+
+    put! {
+        put_u16_le: u16 => to_le_bytes;
+        put_u16_be: u16 => to_be_bytes;
+        put_u32_le: u32 => to_le_bytes;
+        put_u32_be: u32 => to_be_bytes;
+        put_u64_le: u64 => to_le_bytes;
+        put_u64_be: u64 => to_be_bytes;
+        put_u128_le: u128 => to_le_bytes;
+        put_u128_be: u128 => to_be_bytes;
+        put_i16_le: i16 => to_le_bytes;
+        put_i16_be: i16 => to_be_bytes;
+        put_i32_le: i32 => to_le_bytes;
+        put_i32_be: i32 => to_be_bytes;
+        put_i64_le: i64 => to_le_bytes;
+        put_i64_be: i64 => to_be_bytes;
+        put_i128_le: i128 => to_le_bytes;
+        put_i128_be: i128 => to_be_bytes;
+        put_f32_le: f32 => to_le_bytes;
+        put_f32_be: f32 => to_be_bytes;
+        put_f64_le: f64 => to_le_bytes;
+        put_f64_be: f64 => to_be_bytes;
+    }
+
+    /// Appends an unsigned LEB128 varint, 7 bits per byte with the high bit set on all but the last
+    pub fn put_uvarint(&mut self, mut n: u64) {
+        let mut buf = [0u8; 10];
+        let mut len = 0;
+        while n >= 0x80 {
+            buf[len] = n as u8 | 0x80;
+            n >>= 7;
+            len += 1;
+        }
+        buf[len] = n as u8;
+        self.extend_from_slice(&buf[..=len])
+    }
+
+    /// Appends a signed varint the way protobuf encodes `int64`: as its two's complement, so negatives take 10 bytes
+    pub fn put_ivarint(&mut self, n: i64) {
+        self.put_uvarint(n as u64)
+    }
+
+    /// Appends a zig-zag encoded varint, like protobuf's `sint64`, so small negatives stay short
+    pub fn put_svarint(&mut self, n: i64) {
+        self.put_uvarint(((n << 1) ^ (n >> 63)) as u64)
+    }
+
+    /// Reserves a length prefix of the given width, to be filled in by `end_frame` with the length of what follows
+    pub fn begin_frame(&mut self, width: PrefixWidth) -> Frame {
+        let start = self.len();
+        self.extend_from_slice(&[0; 8][..width.size()]);
+        Frame { start, width }
+    }
+
+    /// Appends `n` zero bytes that a `Patch` can fill in later, e.g. with a checksum of what follows
+    pub fn reserve_patch(&mut self, n: usize) -> Patch {
+        let start = self.len();
+        self.extend_zeroed(n);
+        Patch { start, len: n }
+    }
+
+    /// Writes the number of bytes appended since `begin_frame` into the frame's prefix
+    ///
+    /// Panics if that length doesn't fit the prefix, or if the vector was truncated past the prefix.
+    pub fn end_frame(&mut self, frame: Frame) {
+        let Frame { start, width } = frame;
+        let body = start + width.size();
+        assert!(body <= self.len(), "end_frame: the vector was truncated past the frame's prefix");
+        let len = (self.len() - body) as u64;
+        assert!(len <= width.max_len(), "end_frame: a body of {len} bytes doesn't fit a {}-byte prefix", width.size());
+        let prefix = &mut self[start..body];
+        match width {
+            PrefixWidth::U8 => prefix.copy_from_slice(&[len as u8]),
+            PrefixWidth::U16Le => prefix.copy_from_slice(&(len as u16).to_le_bytes()),
+            PrefixWidth::U16Be => prefix.copy_from_slice(&(len as u16).to_be_bytes()),
+            PrefixWidth::U32Le => prefix.copy_from_slice(&(len as u32).to_le_bytes()),
+            PrefixWidth::U32Be => prefix.copy_from_slice(&(len as u32).to_be_bytes()),
+            PrefixWidth::U64Le => prefix.copy_from_slice(&len.to_le_bytes()),
+            PrefixWidth::U64Be => prefix.copy_from_slice(&len.to_be_bytes()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{BumpAlloc, PrefixWidth};
+
+    #[test]
+    fn put() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.put_u8(1);
+        v1.put_i8(-1);
+        v1.put_u16_be(0x0203);
+        v1.put_u32_le(0x07060504);
+        v1.put_i64_be(-2);
+        v1.put_f32_le(1.0);
+        assert_eq!(v1.freeze(), [
+            1, 0xFF, 2, 3, 4, 5, 6, 7,
+            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE,
+            0, 0, 0x80, 0x3F,
+        ]);
+    }
+
+    #[test]
+    fn varint() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.put_uvarint(0);
+        v1.put_uvarint(127);
+        v1.put_uvarint(300);
+        assert_eq!(v1.freeze(), [0, 0x7F, 0xAC, 0x02]);
+        let mut v2 = alloc.top();
+        v2.put_uvarint(u64::MAX);
+        assert_eq!(v2.freeze(), [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]);
+        let mut v3 = alloc.top();
+        v3.put_ivarint(-1);
+        assert_eq!(v3.len(), 10);
+        v3.clear();
+        v3.put_svarint(0);
+        v3.put_svarint(-1);
+        v3.put_svarint(1);
+        v3.put_svarint(-64);
+        v3.put_svarint(i64::MIN);
+        assert_eq!(v3.freeze(), [0, 1, 2, 0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]);
+    }
+
+    #[test]
+    fn frame() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        let outer = v1.begin_frame(PrefixWidth::U16Be);
+        v1.put_u8(0xAA);
+        let inner = v1.begin_frame(PrefixWidth::U32Le);
+        v1.extend_from_slice(b"hello");
+        v1.end_frame(inner);
+        v1.end_frame(outer);
+        let empty = v1.begin_frame(PrefixWidth::U8);
+        v1.end_frame(empty);
+        assert_eq!(v1.freeze(), [0, 10, 0xAA, 5, 0, 0, 0, b'h', b'e', b'l', b'l', b'o', 0]);
+    }
+
+    #[test]
+    fn patch() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.put_u8(1);
+        let offset = v1.reserve_patch(4);
+        let sum = v1.reserve_patch(1);
+        assert_eq!(sum.len(), 1);
+        v1.extend_from_slice(b"abc");
+        let total = v1[6..].iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+        sum.write(&mut v1, &[total]);
+        offset.write(&mut v1, &6u32.to_le_bytes());
+        assert_eq!(v1.freeze(), [1, 6, 0, 0, 0, 0x26, b'a', b'b', b'c']);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't fit a 1-byte prefix")]
+    fn frame_overflow() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        let frame = v1.begin_frame(PrefixWidth::U8);
+        v1.extend_from_slice(&[0; 256]);
+        v1.end_frame(frame);
+    }
+}
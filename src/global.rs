@@ -0,0 +1,111 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::{BumpAlloc, OsPages};
+
+/// A `GlobalAlloc` that hands out every allocation of the program from one arena and never frees
+///
+/// Meant for short-lived tools, which can put everything in the arena with one line:
+///
+/// ```no_run
+/// #[global_allocator]
+/// static GLOBAL: freeze::FreezeGlobal = freeze::FreezeGlobal::new(36);
+/// ```
+///
+/// The arena of `2^bits` bytes is reserved on the first allocation, and a spin lock serializes the threads using it.
+/// Only the most recent allocation grows in place on `realloc`, others are copied; `dealloc` does nothing.
+pub struct FreezeGlobal {
+    bits: u8,
+    lock: AtomicBool,
+    arena: UnsafeCell<Option<BumpAlloc<OsPages>>>,
+}
+
+// The arena is only touched with the lock held.
+unsafe impl Sync for FreezeGlobal {}
+
+impl FreezeGlobal {
+    /// An allocator that will reserve `2^bits` bytes of address space when it's first used
+    pub const fn new(bits: u8) -> Self {
+        FreezeGlobal { bits, lock: AtomicBool::new(false), arena: UnsafeCell::new(None) }
+    }
+
+    /// The number of bytes handed out so far, alignment padding included
+    pub fn allocated(&self) -> usize {
+        self.with_arena(|arena| arena.top_end()).unwrap_or(0)
+    }
+
+    /// Runs `f` on the arena with the lock held, reserving the arena first if needed; `None` if that fails
+    fn with_arena<R>(&self, f: impl FnOnce(&mut BumpAlloc<OsPages>) -> R) -> Option<R> {
+        while self.lock.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            core::hint::spin_loop();
+        }
+        let arena = unsafe { &mut *self.arena.get() };
+        if arena.is_none() {
+            *arena = BumpAlloc::try_new_with_address_space(self.bits).ok();
+        }
+        let res = arena.as_mut().map(f);
+        self.lock.store(false, Ordering::Release);
+        res
+    }
+}
+
+unsafe impl GlobalAlloc for FreezeGlobal {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.with_arena(|arena| {
+            let padding = arena.top_base.align_offset(layout.align());
+            arena.try_ensure(padding.saturating_add(layout.size())).ok()?;
+            unsafe {
+                let ptr = arena.top_base.add(padding);
+                arena.top_base = ptr.add(layout.size());
+                Some(ptr)
+            }
+        }).flatten().unwrap_or(core::ptr::null_mut())
+    }
+
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {}
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let in_place = self.with_arena(|arena| unsafe {
+            if new_size <= layout.size() {
+                return true;
+            }
+            if ptr.add(layout.size()) != arena.top_base || arena.try_ensure(new_size - layout.size()).is_err() {
+                return false;
+            }
+            arena.top_base = ptr.add(new_size);
+            true
+        });
+        if in_place == Some(true) {
+            return ptr;
+        }
+        let new = unsafe { self.alloc(Layout::from_size_align_unchecked(new_size, layout.align())) };
+        if !new.is_null() {
+            unsafe { core::ptr::copy_nonoverlapping(ptr, new, layout.size()) };
+        }
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global() {
+        static GLOBAL: FreezeGlobal = FreezeGlobal::new(20);
+        assert_eq!(GLOBAL.allocated(), 0);
+        unsafe {
+            let a = GLOBAL.alloc(Layout::new::<u8>());
+            let b = GLOBAL.alloc(Layout::new::<u64>());
+            assert_eq!((b as usize % 8, GLOBAL.allocated()), (0, 16));
+            b.cast::<u64>().write(7);
+            let grown = GLOBAL.realloc(b, Layout::new::<u64>(), 32);
+            assert_eq!((grown, grown.cast::<u64>().read(), GLOBAL.allocated()), (b, 7, 40));
+            a.write(1);
+            let moved = GLOBAL.realloc(a, Layout::new::<u8>(), 2);
+            assert_eq!((moved.read(), GLOBAL.allocated()), (1, 42));
+            GLOBAL.dealloc(moved, Layout::new::<[u8; 2]>());
+            assert!(GLOBAL.alloc(Layout::from_size_align(1 << 21, 1).unwrap()).is_null());
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use core::hash::Hasher;
+use crate::{CapacityError, LiquidVecRef};
+
+/// A hash function fed a stream of bytes, whose digest can be of any size
+///
+/// Every `Hasher` is one, with its `u64` as the digest; a cryptographic hash like SHA-256 goes in a small wrapper
+/// whose `Output` is its byte array.
+pub trait Digest {
+    /// The digest, like `u64` or `[u8; 32]`
+    type Output;
+
+    /// Feeds more bytes, split writes giving the same digest as one
+    fn update(&mut self, bytes: &[u8]);
+
+    /// The digest of all bytes fed so far
+    fn finalize(self) -> Self::Output;
+}
+
+impl<H: Hasher> Digest for H {
+    type Output = u64;
+
+    #[inline(always)]
+    fn update(&mut self, bytes: &[u8]) {
+        self.write(bytes)
+    }
+
+    #[inline(always)]
+    fn finalize(self) -> u64 {
+        self.finish()
+    }
+}
+
+/// Appends to a byte vector while feeding the same bytes to a hasher, so the digest is ready when it's frozen
+///
+/// Only bytes appended through the writer are hashed, not what the vector held before. Any `Digest` works, which
+/// includes every `Hasher` that treats `write` as a stream of bytes.
+pub struct HashingWriter<'alloc, 'data, H: Digest> {
+    vec: LiquidVecRef<'alloc, 'data, u8>,
+    hasher: H,
+}
+
+impl<'alloc, 'data, H: Digest> HashingWriter<'alloc, 'data, H> {
+    /// Appends to `vec` after whatever it already holds, feeding `hasher`
+    #[inline(always)]
+    pub fn new(vec: LiquidVecRef<'alloc, 'data, u8>, hasher: H) -> Self {
+        HashingWriter { vec, hasher }
+    }
+
+    /// Appends a byte, panicking when the arena is full
+    #[inline(always)]
+    pub fn push(&mut self, b: u8) {
+        self.extend_from_slice(&[b])
+    }
+
+    /// Appends bytes, panicking when the arena is full
+    #[inline(always)]
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        if let Err(e) = self.try_extend_from_slice(bytes) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `extend_from_slice`, but returns an error instead of panicking when the arena is full, hashing nothing
+    #[inline(always)]
+    pub fn try_extend_from_slice(&mut self, bytes: &[u8]) -> Result<(), CapacityError> {
+        self.vec.try_extend_from_slice(bytes)?;
+        self.hasher.update(bytes);
+        Ok(())
+    }
+
+    /// The bytes in the vector
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.vec
+    }
+
+    /// The hasher, which has seen every byte appended so far
+    #[inline(always)]
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Freeze the vector, returning it with the digest of the bytes appended
+    #[inline(always)]
+    pub fn freeze_with_digest(self) -> (&'data mut [u8], H::Output) {
+        (self.vec.freeze(), self.hasher.finalize())
+    }
+
+    /// Unwraps the vector and the hasher
+    #[inline(always)]
+    pub fn into_parts(self) -> (LiquidVecRef<'alloc, 'data, u8>, H) {
+        (self.vec, self.hasher)
+    }
+}
+
+/// CRC-32 as used by zlib, gzip and PNG (IEEE polynomial, reflected), as a `Hasher` whose `finish` is the checksum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32(u32);
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { crc >> 1 ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl Crc32 {
+    /// A checksum with no bytes written yet
+    pub const fn new() -> Self {
+        Crc32(!0)
+    }
+
+    /// The checksum of `bytes` in one go
+    pub fn checksum(bytes: &[u8]) -> u32 {
+        let mut crc = Self::new();
+        crc.write(bytes);
+        crc.value()
+    }
+
+    /// The checksum of the bytes written so far
+    pub fn value(&self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for Crc32 {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0 >> 8 ^ CRC32_TABLE[(self.0 as u8 ^ b) as usize];
+        }
+    }
+
+    #[inline(always)]
+    fn finish(&self) -> u64 {
+        self.value() as u64
+    }
+}
+
+/// Writes append to the vector and are hashed; running out of arena is reported as `ErrorKind::OutOfMemory`
+#[cfg(feature = "std")]
+impl<'alloc, 'data, H: Digest> std::io::Write for HashingWriter<'alloc, 'data, H> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.try_extend_from_slice(buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::OutOfMemory, e))
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Formatting appends to the vector and is hashed; running out of arena is reported as `fmt::Error`
+impl<'alloc, 'data, H: Digest> core::fmt::Write for HashingWriter<'alloc, 'data, H> {
+    #[inline(always)]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.try_extend_from_slice(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::hash::{DefaultHasher, Hasher};
+    use std::io::Write;
+    use crate::BumpAlloc;
+    use super::{Crc32, Digest, HashingWriter};
+
+    /// Fletcher-16, standing in for a hash with a byte array digest
+    struct Fletcher16(u16, u16);
+
+    impl Digest for Fletcher16 {
+        type Output = [u8; 2];
+
+        fn update(&mut self, bytes: &[u8]) {
+            for &b in bytes {
+                self.0 = (self.0 + b as u16) % 255;
+                self.1 = (self.1 + self.0) % 255;
+            }
+        }
+
+        fn finalize(self) -> [u8; 2] {
+            [self.1 as u8, self.0 as u8]
+        }
+    }
+
+    #[test]
+    fn hashing_writer() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v = alloc.top();
+        v.extend_from_slice(b"header ");
+        let mut w = HashingWriter::new(v, DefaultHasher::new());
+        w.extend_from_slice(b"hello");
+        w.push(b',');
+        let name = "world";
+        write!(w, " {name}").unwrap();
+        let (bytes, digest) = w.freeze_with_digest();
+        assert_eq!(bytes, b"header hello, world");
+
+        let mut expected = DefaultHasher::new();
+        expected.write(b"hello, world");
+        assert_eq!(digest, expected.finish());
+
+        let mut w = HashingWriter::new(alloc.top(), Crc32::new());
+        w.extend_from_slice(b"12345");
+        w.extend_from_slice(b"6789");
+        assert_eq!(w.freeze_with_digest().1, 0xcbf4_3926);
+        assert_eq!(Crc32::checksum(b""), 0);
+
+        let mut w = HashingWriter::new(alloc.top(), Fletcher16(0, 0));
+        w.extend_from_slice(b"ab");
+        w.extend_from_slice(b"cde");
+        let (bytes, digest) = w.freeze_with_digest();
+        assert_eq!((&*bytes, digest), (&b"abcde"[..], [0xc8, 0xf0]));
+    }
+}
@@ -0,0 +1,295 @@
+use std::io::{self, Read};
+use core::ops::{Bound, RangeBounds};
+use crate::{BumpAlloc, CapacityError, LiquidVecRef, PageSource};
+
+/// Bytes offered to a reader at a time
+const CHUNK: usize = 64 * 1024;
+
+fn out_of_memory(e: CapacityError) -> io::Error {
+    io::Error::new(io::ErrorKind::OutOfMemory, e)
+}
+
+impl<'alloc, 'data> LiquidVecRef<'alloc, 'data, u8> {
+    /// Reads from `r` straight into the arena until the end of its input, or until `limit` bytes if given, returning
+    /// how many bytes were appended
+    ///
+    /// There's no buffer in between: the reader is handed the memory after the vector. Interrupted reads are retried.
+    /// Running out of arena is reported as `ErrorKind::OutOfMemory`; on that or any other error the bytes read until
+    /// then stay appended.
+    pub fn extend_from_reader(&mut self, mut r: impl Read, limit: Option<usize>) -> io::Result<usize> {
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut total = 0;
+        while total < limit {
+            let max = (limit - total).min(CHUNK);
+            match self.read_chunk("LiquidVecRef::extend_from_reader", max, true, |ptr, len| {
+                r.read(unsafe { core::slice::from_raw_parts_mut(ptr, len) })
+            }) {
+                Ok(0) => break,
+                Ok(n) => total += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(total)
+    }
+
+    /// Appends the buffers one after the other, as gathered by `write_vectored`, panicking when the arena is full
+    #[inline(always)]
+    pub fn extend_from_slices(&mut self, bufs: &[io::IoSlice<'_>]) {
+        self.extend_from_chunks(bufs.iter().map(|buf| &**buf))
+    }
+
+    /// Like `extend_from_slices`, but returns an error instead of panicking when the arena is full, appending nothing
+    #[inline(always)]
+    pub fn try_extend_from_slices(&mut self, bufs: &[io::IoSlice<'_>]) -> Result<(), CapacityError> {
+        self.try_extend_from_chunks(bufs.iter().map(|buf| &**buf))
+    }
+
+    /// Reads up to `max` bytes from `fd` straight into the arena with a single `read` call, returning how many bytes
+    /// were appended, 0 at the end of the input
+    ///
+    /// On a socket that's a `recv` without flags, so each call appends at most one datagram, which can then be frozen
+    /// as a message. Interrupted calls are retried, other errors like `ErrorKind::WouldBlock` are returned as is.
+    /// Running out of arena is reported as `ErrorKind::OutOfMemory`.
+    #[cfg(unix)]
+    pub fn extend_from_fd(&mut self, fd: impl std::os::fd::AsFd, max: usize) -> io::Result<usize> {
+        use std::os::fd::AsRawFd;
+        let fd = fd.as_fd().as_raw_fd();
+        loop {
+            let read = self.read_chunk("LiquidVecRef::extend_from_fd", max, false, |ptr, len| unsafe {
+                crate::sys::read_fd(fd, ptr, len).map_err(io::Error::from_raw_os_error)
+            });
+            match read {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                read => return read,
+            }
+        }
+    }
+
+    /// Hands `read` up to `max` bytes of memory after the vector, fewer if that's all the arena has left, and appends
+    /// the bytes it reports filling
+    ///
+    /// With `init` the memory is zeroed first where the arena has written before, so it can be passed on as a `&mut
+    /// [u8]`; the OS doesn't need that.
+    pub(crate) fn read_chunk(&mut self, op: &str, max: usize, init: bool,
+                             read: impl FnOnce(*mut u8, usize) -> io::Result<usize>) -> io::Result<usize> {
+        self.alloc.check(op);
+        let len = match self.alloc.try_ensure(max) {
+            Ok(()) => max,
+            Err(e) if e.errno() == 0 && e.remaining() != 0 && e.remaining() < max => {
+                self.alloc.try_ensure(e.remaining()).map_err(out_of_memory)?;
+                e.remaining()
+            }
+            Err(e) => return Err(out_of_memory(e)),
+        };
+        let end = self.alloc.top_end();
+        if init {
+            let dirty = self.alloc.zeroed_from().saturating_sub(end).min(len);
+            unsafe { self.end().write_bytes(0, dirty) };
+        }
+        self.alloc.dirty = self.alloc.dirty.max(end + len);
+        let n = read(self.end(), len)?;
+        assert!(n <= len, "{op}: read {n} bytes into a buffer of {len}");
+        self.alloc.top_size += n;
+        Ok(n)
+    }
+}
+
+impl<S: ?Sized + PageSource> BumpAlloc<S> {
+    /// The frozen bytes between the offsets in `range`, ready for `write_vectored` or `sendmsg`
+    ///
+    /// With a registry (see `with_registry`) each frozen allocation is its own slice, leaving out the padding and
+    /// canaries between them; otherwise the whole frozen part of the range is one slice. This takes the `BumpAlloc`
+    /// like `frozen_slices`, so no frozen slice can be written to while they're read.
+    pub fn io_slices(&self, range: impl RangeBounds<usize>) -> impl Iterator<Item = io::IoSlice<'_>> + '_ {
+        let frozen = unsafe { self.top_base.offset_from(self.data_base) as usize };
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => usize::MAX,
+        }.min(frozen);
+        let records = self.registry.iter().flatten().map(|record| (record.offset, record.len));
+        let whole = self.registry.is_none().then_some((0, frozen));
+        records.chain(whole).filter_map(move |(offset, len)| {
+            let (from, to) = (offset.max(start), (offset + len).min(end));
+            (from < to).then(|| io::IoSlice::new(unsafe {
+                core::slice::from_raw_parts(self.data_base.add(from), to - from)
+            }))
+        })
+    }
+
+    /// Writes everything allocated so far, the top vector included, to `w` in one go, returning how many bytes that is
+    ///
+    /// The bytes are the same `into_boxed_bytes` would return, without copying them first.
+    pub fn dump_to(&self, w: &mut impl io::Write) -> io::Result<usize> {
+        let bytes = unsafe { core::slice::from_raw_parts(self.data_base, self.top_end()) };
+        w.write_all(bytes)?;
+        Ok(bytes.len())
+    }
+
+    /// Like `dump_to`, writing straight to a file or socket with as few `write` calls as it takes
+    #[cfg(unix)]
+    pub fn dump_to_fd(&self, fd: impl std::os::fd::AsFd) -> io::Result<usize> {
+        use std::os::fd::AsRawFd;
+        let fd = fd.as_fd().as_raw_fd();
+        let len = self.top_end();
+        let mut written = 0;
+        while written < len {
+            match unsafe { crate::sys::write_fd(fd, self.data_base.add(written), len - written) } {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(errno) if io::Error::from_raw_os_error(errno).kind() == io::ErrorKind::Interrupted => {}
+                Err(errno) => return Err(io::Error::from_raw_os_error(errno)),
+            }
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+    use crate::BumpAlloc;
+
+    #[test]
+    fn extend_from_reader() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v = alloc.top();
+        v.extend_from_slice(b">");
+        assert_eq!(v.extend_from_reader(&b"hello world"[..], Some(5)).unwrap(), 5);
+        assert_eq!(v.extend_from_reader(io::repeat(7).take(200_000), None).unwrap(), 200_000);
+        assert_eq!(v.len(), 200_006);
+        assert_eq!(&v[..6], b">hello");
+        assert!(v[6..].iter().all(|&b| b == 7));
+
+        let mut small = BumpAlloc::new_with_address_space(12);
+        let mut small = small.to_ref();
+        let mut v = small.top();
+        let err = v.extend_from_reader(io::repeat(1), None).unwrap_err();
+        assert_eq!((err.kind(), v.len()), (io::ErrorKind::OutOfMemory, 4096));
+    }
+
+    #[test]
+    fn extend_from_slices() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v = alloc.top();
+        v.extend_from_slices(&[io::IoSlice::new(b"GET "), io::IoSlice::new(b""), io::IoSlice::new(b"/ HTTP/1.1")]);
+        v.extend_from_chunks(b"\r\nHost: a\r\n".split_inclusive(|&b| b == b'\n'));
+        assert_eq!(v.freeze(), b"GET / HTTP/1.1\r\nHost: a\r\n");
+
+        let mut small = BumpAlloc::new_with_address_space(12);
+        let mut small = small.to_ref();
+        let mut v = small.top_of::<u32>();
+        assert!(v.try_extend_from_chunks([&[1, 2][..], &[0; 1023]]).is_err());
+        v.extend_from_chunks([&[1, 2][..], &[3; 1022]]);
+        assert_eq!((v.len(), v[1], v[1023]), (1024, 2, 3));
+
+        /// Yields a single chunk, a long one from the original and a short one from its clones
+        struct Grows { long: bool, done: bool }
+        impl Clone for Grows {
+            fn clone(&self) -> Self {
+                Grows { long: false, done: self.done }
+            }
+        }
+        impl Iterator for Grows {
+            type Item = &'static [u8];
+            fn next(&mut self) -> Option<&'static [u8]> {
+                if core::mem::replace(&mut self.done, true) {
+                    return None;
+                }
+                Some(if self.long { &[2; 1 << 16] } else { &[1] })
+            }
+        }
+        let mut small = BumpAlloc::new_with_address_space(12);
+        let mut small = small.to_ref();
+        let mut v = small.top();
+        v.extend_from_chunks(Grows { long: true, done: false });
+        assert_eq!(*v, [2]);
+    }
+
+    #[test]
+    fn io_slices() {
+        let mut alloc = BumpAlloc::new().with_registry();
+        let mut alloc_ref = alloc.to_ref();
+        alloc_ref.alloc_slice_copy(b"abc");
+        alloc_ref.alloc(0x0102_0304u32);
+        alloc_ref.alloc_slice_copy(b"de");
+        alloc_ref.top().extend_from_slice(b"not frozen");
+        let lens = |slices: &[io::IoSlice]| slices.iter().map(|s| s.len()).collect::<Vec<_>>();
+        let slices: Vec<_> = alloc.io_slices(..).collect();
+        assert_eq!(lens(&slices), [3, 4, 2]);
+        let mut out = Vec::new();
+        assert_eq!(io::Write::write_vectored(&mut out, &slices).unwrap(), 9);
+        assert_eq!(&out[..3], b"abc");
+        assert_eq!(lens(&alloc.io_slices(2..=5).collect::<Vec<_>>()), [1, 2]);
+        assert_eq!((alloc.io_slices(9..).count(), alloc.io_slices(10..).count()), (1, 0));
+
+        let mut plain = BumpAlloc::new();
+        plain.to_ref().alloc_slice_copy(b"abc");
+        plain.to_ref().alloc(1u32);
+        assert_eq!(lens(&plain.io_slices(1..).collect::<Vec<_>>()), [7]);
+    }
+
+    #[test]
+    fn dump_to() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc_ref = alloc.to_ref();
+        alloc_ref.alloc_slice_copy(b"frozen ");
+        alloc_ref.top().extend_from_slice(b"and top");
+        let mut out = Vec::new();
+        assert_eq!(alloc.dump_to(&mut out).unwrap(), 14);
+        assert_eq!(out, b"frozen and top");
+
+        #[cfg(unix)]
+        {
+            use std::io::Read;
+            let (tx, mut rx) = std::os::unix::net::UnixStream::pair().unwrap();
+            let mut big = BumpAlloc::new();
+            big.to_ref().alloc_slice_fill_copy(1 << 20, 7u8);
+            let reader = std::thread::spawn(move || {
+                let mut out = Vec::new();
+                rx.read_to_end(&mut out).map(|_| out)
+            });
+            assert_eq!(big.dump_to_fd(&tx).unwrap(), 1 << 20);
+            drop(tx);
+            let out = reader.join().unwrap().unwrap();
+            assert!(out.len() == 1 << 20 && out.iter().all(|&b| b == 7));
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn extend_from_fd() {
+        use std::io::Write;
+        use std::os::unix::net::{UnixDatagram, UnixStream};
+
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let (tx, rx) = UnixDatagram::pair().unwrap();
+        tx.send(b"first").unwrap();
+        tx.send(b"second").unwrap();
+        let mut messages = Vec::new();
+        for _ in 0..2 {
+            let mut v = alloc.top();
+            v.extend_from_fd(&rx, 1500).unwrap();
+            messages.push(v.freeze_shared());
+        }
+        assert_eq!(messages, [&b"first"[..], b"second"]);
+
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        tx.write_all(b"abcdef").unwrap();
+        drop(tx);
+        let mut v = alloc.top();
+        assert_eq!(v.extend_from_fd(&rx, 4).unwrap(), 4);
+        assert_eq!(v.extend_from_fd(&rx, 4).unwrap(), 2);
+        assert_eq!(v.extend_from_fd(&rx, 4).unwrap(), 0);
+        assert_eq!(*v, *b"abcdef");
+    }
+}
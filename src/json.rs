@@ -0,0 +1,306 @@
+use core::fmt::Write;
+use crate::LiquidStringRef;
+
+/// Objects and arrays can be nested this deep
+const MAX_DEPTH: u32 = 64;
+
+/// Writes JSON into a string in the arena, adding commas and escapes, and freezes it once it's complete
+///
+/// Structure is checked as it's written: keys only go in objects, every value in an object follows a key, and
+/// objects and arrays are closed in order. Mistakes panic, as does running out of arena, like `LiquidStringRef::push`.
+pub struct JsonWriter<'alloc, 'data> {
+    out: LiquidStringRef<'alloc, 'data>,
+    /// A bit per open container, 1 for an object, innermost in the lowest bit
+    stack: u64,
+    depth: u32,
+    /// Nothing has been written in the innermost container yet, or at all at the top level
+    first: bool,
+    after_key: bool,
+}
+
+/// A value that can be written with `JsonWriter::value`
+pub trait JsonValue {
+    /// Writes the value with `w`, which may take several calls for a container
+    fn write_json(&self, w: &mut JsonWriter<'_, '_>);
+}
+
+impl<'alloc, 'data> JsonWriter<'alloc, 'data> {
+    /// Writes after whatever `out` already holds, which is usually nothing
+    pub fn new(out: LiquidStringRef<'alloc, 'data>) -> Self {
+        JsonWriter { out, stack: 0, depth: 0, first: true, after_key: false }
+    }
+
+    /// Freeze the JSON written, finishing the allocation
+    ///
+    /// Panics if an object or array is still open.
+    pub fn freeze(self) -> &'data mut str {
+        assert!(self.depth == 0, "JsonWriter::freeze: {} objects or arrays are still open", self.depth);
+        self.out.freeze()
+    }
+
+    /// The JSON written so far
+    #[inline(always)]
+    pub fn as_str(&self) -> &str {
+        self.out.as_str()
+    }
+
+    /// Opens an object, which takes keys and values until `end_object`
+    pub fn begin_object(&mut self) {
+        self.begin_container(true);
+        self.out.push('{');
+    }
+
+    /// Closes the innermost object
+    pub fn end_object(&mut self) {
+        assert!(self.in_object(), "JsonWriter::end_object: not in an object");
+        assert!(!self.after_key, "JsonWriter::end_object: the last key has no value");
+        self.end_container();
+        self.out.push('}');
+    }
+
+    /// Opens an array, which takes values until `end_array`
+    pub fn begin_array(&mut self) {
+        self.begin_container(false);
+        self.out.push('[');
+    }
+
+    /// Closes the innermost array
+    pub fn end_array(&mut self) {
+        assert!(self.depth > 0 && !self.in_object(), "JsonWriter::end_array: not in an array");
+        self.end_container();
+        self.out.push(']');
+    }
+
+    /// Writes the key for the next value in an object
+    pub fn key(&mut self, key: &str) {
+        assert!(self.in_object() && !self.after_key, "JsonWriter::key: a key must follow a value in an object");
+        if !self.first {
+            self.out.push(',');
+        }
+        self.first = false;
+        self.write_string(key);
+        self.out.push(':');
+        self.after_key = true;
+    }
+
+    /// Writes a value, as an element of an array, after a key in an object, or as the whole document
+    pub fn value<V: JsonValue + ?Sized>(&mut self, value: &V) {
+        value.write_json(self)
+    }
+
+    /// Writes a key and its value
+    #[inline(always)]
+    pub fn field<V: JsonValue + ?Sized>(&mut self, key: &str, value: &V) {
+        self.key(key);
+        self.value(value)
+    }
+
+    /// Writes a value that's already JSON, as is
+    pub fn raw_value(&mut self, json: &str) {
+        self.begin_value();
+        self.out.push_str(json)
+    }
+
+    /// Writes `null`
+    pub fn null(&mut self) {
+        self.raw_value("null")
+    }
+
+    /// Writes `true` or `false`
+    pub fn bool(&mut self, value: bool) {
+        self.raw_value(if value { "true" } else { "false" })
+    }
+
+    /// Writes a string, quoted and escaped
+    pub fn string(&mut self, value: &str) {
+        self.begin_value();
+        self.write_string(value)
+    }
+
+    /// Writes a number in its shortest form that reads back the same, or `null` if it's infinite or NaN, which JSON
+    /// can't represent
+    pub fn f64(&mut self, value: f64) {
+        if value.is_finite() {
+            self.begin_value();
+            self.write_fmt(format_args!("{value:?}"))
+        } else {
+            self.null()
+        }
+    }
+
+    /// Writes a signed integer
+    pub fn i64(&mut self, value: i64) {
+        self.begin_value();
+        self.write_fmt(format_args!("{value}"))
+    }
+
+    /// Writes an unsigned integer
+    pub fn u64(&mut self, value: u64) {
+        self.begin_value();
+        self.write_fmt(format_args!("{value}"))
+    }
+
+    #[inline(always)]
+    fn in_object(&self) -> bool {
+        self.depth > 0 && self.stack & 1 == 1
+    }
+
+    /// Adds the comma before an array element, after checking a value can go here
+    fn begin_value(&mut self) {
+        if self.after_key {
+            self.after_key = false;
+            return;
+        }
+        assert!(!self.in_object(), "JsonWriter: a value in an object needs a key first");
+        assert!(self.depth > 0 || self.first, "JsonWriter: there's already a top-level value");
+        if !self.first {
+            self.out.push(',');
+        }
+        self.first = false;
+    }
+
+    fn begin_container(&mut self, object: bool) {
+        self.begin_value();
+        assert!(self.depth < MAX_DEPTH, "JsonWriter: nested more than {MAX_DEPTH} deep");
+        self.stack = self.stack << 1 | object as u64;
+        self.depth += 1;
+        self.first = true;
+    }
+
+    fn end_container(&mut self) {
+        self.stack >>= 1;
+        self.depth -= 1;
+        self.first = false;
+    }
+
+    /// Writes `s` quoted, escaping quotes, backslashes and control characters
+    fn write_string(&mut self, s: &str) {
+        self.out.push('"');
+        let mut start = 0;
+        for (i, b) in s.bytes().enumerate() {
+            let escape = match b {
+                b'"' => "\\\"",
+                b'\\' => "\\\\",
+                b'\n' => "\\n",
+                b'\r' => "\\r",
+                b'\t' => "\\t",
+                0..0x20 => "",
+                _ => continue,
+            };
+            self.out.push_str(&s[start..i]);
+            if escape.is_empty() {
+                self.write_fmt(format_args!("\\u{b:04x}"));
+            } else {
+                self.out.push_str(escape);
+            }
+            start = i + 1;
+        }
+        self.out.push_str(&s[start..]);
+        self.out.push('"');
+    }
+
+    fn write_fmt(&mut self, args: core::fmt::Arguments) {
+        if self.out.write_fmt(args).is_err() {
+            panic!("JsonWriter: the arena is exhausted")
+        }
+    }
+}
+
+impl JsonValue for str {
+    fn write_json(&self, w: &mut JsonWriter<'_, '_>) {
+        w.string(self)
+    }
+}
+
+impl JsonValue for bool {
+    fn write_json(&self, w: &mut JsonWriter<'_, '_>) {
+        w.bool(*self)
+    }
+}
+
+macro_rules! json_number {
+    ($method:ident: $($t:ty),*) => {$(
+        impl JsonValue for $t {
+            #[inline(always)]
+            fn write_json(&self, w: &mut JsonWriter<'_, '_>) {
+                w.$method((*self).into())
+            }
+        }
+    )*};
+}
+
+json_number!(i64: i8, i16, i32, i64);
+json_number!(u64: u8, u16, u32, u64);
+json_number!(f64: f32, f64);
+
+impl<V: JsonValue> JsonValue for Option<V> {
+    fn write_json(&self, w: &mut JsonWriter<'_, '_>) {
+        match self {
+            Some(value) => value.write_json(w),
+            None => w.null(),
+        }
+    }
+}
+
+/// Slices and arrays are written as JSON arrays
+impl<V: JsonValue> JsonValue for [V] {
+    fn write_json(&self, w: &mut JsonWriter<'_, '_>) {
+        w.begin_array();
+        for value in self {
+            value.write_json(w);
+        }
+        w.end_array()
+    }
+}
+
+impl<V: JsonValue, const N: usize> JsonValue for [V; N] {
+    #[inline(always)]
+    fn write_json(&self, w: &mut JsonWriter<'_, '_>) {
+        self[..].write_json(w)
+    }
+}
+
+impl<V: JsonValue + ?Sized> JsonValue for &V {
+    #[inline(always)]
+    fn write_json(&self, w: &mut JsonWriter<'_, '_>) {
+        (**self).write_json(w)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::BumpAlloc;
+    use super::JsonWriter;
+
+    #[test]
+    fn json() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut w = JsonWriter::new(alloc.top_string());
+        w.begin_object();
+        w.field("name", "a \"quoted\"\\\n\u{1}é");
+        w.field("ids", &[1u32, 2, 3]);
+        w.key("nested");
+        w.begin_array();
+        w.begin_object();
+        w.end_object();
+        w.value(&None::<bool>);
+        w.value(&[-1.5, f64::NAN]);
+        w.raw_value("{\"x\":1}");
+        w.end_array();
+        w.field("ok", &true);
+        w.end_object();
+        let json = w.freeze();
+        assert_eq!(json, r#"{"name":"a \"quoted\"\\\n\u0001é","ids":[1,2,3],"nested":[{},null,[-1.5,null],{"x":1}],"ok":true}"#);
+    }
+
+    #[test]
+    #[should_panic = "needs a key first"]
+    fn json_missing_key() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut w = JsonWriter::new(alloc.top_string());
+        w.begin_object();
+        w.value(&1u8);
+    }
+}
@@ -0,0 +1,1483 @@
+use core::slice::SliceIndex;
+use core::ops::{Bound, RangeBounds};
+use core::ops::{Deref, DerefMut};
+use core::marker::PhantomData;
+use crate::{ArenaSpan, BumpAlloc, CapacityError, PageSource};
+use crate::arena::COMMIT_GRANULARITY;
+
+/// The vector on top of a `BumpAlloc`, the only one that can still grow
+///
+/// Elements are stored unpadded in the arena, so a `LiquidVecRef<T>` freezes into a plain `&mut [T]`.
+#[repr(transparent)]
+pub struct LiquidVecRef<'alloc, 'data, T = u8> {
+    pub(crate) alloc: &'alloc mut BumpAlloc<dyn PageSource>,
+    pub(crate) _data: PhantomData<&'data mut [T]>,
+}
+
+impl <'alloc, 'data, T: Copy> LiquidVecRef<'alloc, 'data, T> {
+    /// ```compile_fail
+    /// use freeze::{BumpAlloc};
+    /// let mut allocb = BumpAlloc::new();
+    /// let mut alloc = allocb.to_ref();
+    /// let mut v1 = alloc.top();
+    /// v1.extend_from_slice(&[42]);
+    /// let slice = v1.freeze();
+    /// drop(allocb);
+    /// let _ = slice.len(); // should fail
+    /// ```
+    /// Consume the vector and produce a slice that can still be used; it's length is now fixed
+    #[inline(always)]
+    pub fn freeze(self) -> &'data mut [T] {
+        self.alloc.check("LiquidVecRef::freeze");
+        let len = self.len();
+        let start = self.alloc.freeze_top() as *mut T;
+        core::mem::forget(self);
+        unsafe { &mut *core::ptr::slice_from_raw_parts_mut(start, len) }
+    }
+
+    /// Freeze, recording `tag` with the allocation so it can be found with `BumpAlloc::find_by_tag`
+    ///
+    /// Tags are kept in the allocator's registry, and dropped if it doesn't keep one (see `BumpAlloc::with_registry`).
+    #[inline(always)]
+    pub fn freeze_tagged(self, tag: u32) -> &'data mut [T] {
+        self.alloc.check("LiquidVecRef::freeze_tagged");
+        let len = self.len();
+        let start = self.alloc.freeze_top() as *mut T;
+        self.alloc.tag_last(tag);
+        core::mem::forget(self);
+        unsafe { &mut *core::ptr::slice_from_raw_parts_mut(start, len) }
+    }
+
+    /// Freeze into an `ArenaSpan`, a position in the arena rather than a reference to it
+    #[inline(always)]
+    pub fn freeze_span(self) -> ArenaSpan {
+        self.alloc.check("LiquidVecRef::freeze_span");
+        let len = self.alloc.top_size;
+        let start = self.alloc.freeze_top();
+        let offset = unsafe { start.offset_from(self.alloc.data_base) as usize };
+        let generation = self.alloc.generation;
+        core::mem::forget(self);
+        ArenaSpan::new(offset, len).with_generation(generation)
+    }
+
+    /// Freeze, then skip ahead so the next allocation starts at a multiple of `align` bytes
+    ///
+    /// Panics if `align` isn't a power of two.
+    pub fn freeze_aligned(self, align: usize) -> &'data mut [T] {
+        self.alloc.check("LiquidVecRef::freeze_aligned");
+        assert!(align.is_power_of_two(), "freeze_aligned: {align} is not a power of two");
+        let len = self.len();
+        let start = self.alloc.freeze_top() as *mut T;
+        self.alloc.align_top(align);
+        core::mem::forget(self);
+        unsafe { &mut *core::ptr::slice_from_raw_parts_mut(start, len) }
+    }
+
+    /// Same as `freeze`, spelled out for symmetry with `freeze_shared`
+    #[inline(always)]
+    pub fn freeze_mut(self) -> &'data mut [T] {
+        self.freeze()
+    }
+
+    /// Freeze into a shared slice, which can be handed to several readers at once
+    #[inline(always)]
+    pub fn freeze_shared(self) -> &'data [T] {
+        self.freeze()
+    }
+
+    /// Freezes the first `mid` elements as a finished allocation, the rest stays in the vector
+    ///
+    /// Panics if `mid > len`. The remaining elements are only moved when the arena puts canaries between allocations.
+    pub fn split_to(&mut self, mid: usize) -> &'data mut [T] {
+        self.alloc.check("LiquidVecRef::split_to");
+        assert!(mid <= self.len(), "split_to: mid {mid} is out of bounds for a vector of {} elements", self.len());
+        let prefix = mid*size_of::<T>();
+        let suffix = self.alloc.top_size - prefix;
+        let gap = if self.alloc.canaries { 8usize.next_multiple_of(align_of::<T>()) } else { 0 };
+        if gap != 0 {
+            self.alloc.ensure(gap);
+            unsafe { core::ptr::copy(self.alloc.top_base.add(prefix), self.alloc.top_base.add(prefix + gap), suffix) };
+        }
+        self.alloc.top_size = prefix;
+        let start = self.alloc.freeze_top() as *mut T;
+        unsafe {
+            self.alloc.top_base = start.add(mid).cast::<u8>().add(gap);
+            self.alloc.top_size = suffix;
+            &mut *core::ptr::slice_from_raw_parts_mut(start, mid)
+        }
+    }
+
+    /// Freezes the elements from `at` on as a finished allocation, the first `at` stay in the vector
+    ///
+    /// Panics if `at > len`. The frozen allocation has to come first in the arena, so the elements are rotated in
+    /// place before `split_to` takes the tail off; use `split_to` when freezing the head will do.
+    pub fn split_off(&mut self, at: usize) -> &'data mut [T] {
+        let len = self.len();
+        assert!(at <= len, "split_off: at {at} is out of bounds for a vector of {len} elements");
+        self.rotate_left(at);
+        self.split_to(len - at)
+    }
+
+    /// Consume the vector and give everything appended since the last freeze back to the arena
+    #[inline(always)]
+    pub fn discard(self) {
+        self.alloc.check("LiquidVecRef::discard");
+        self.alloc.shrink_top(0);
+        core::mem::forget(self);
+    }
+
+    #[inline(always)]
+    pub(crate) fn end(&self) -> *mut T {
+        unsafe { self.alloc.top_base.add(self.alloc.top_size) as *mut T }
+    }
+
+    #[inline(always)]
+    pub(crate) fn extend_one(&mut self, item: T) {
+        self.alloc.check("LiquidVecRef::extend_one");
+        self.alloc.ensure(size_of::<T>());
+        unsafe { self.write_one(item) }
+    }
+
+    /// Like `extend_one`, but returns an error instead of panicking when the arena is full
+    #[inline(always)]
+    pub fn try_extend_one(&mut self, item: T) -> Result<(), CapacityError> {
+        self.alloc.check("LiquidVecRef::try_extend_one");
+        self.alloc.try_ensure(size_of::<T>())?;
+        unsafe { self.write_one(item) }
+        Ok(())
+    }
+
+    /// Appends `item`, panicking when the arena is full
+    #[inline(always)]
+    pub fn push(&mut self, item: T) {
+        self.extend_one(item)
+    }
+
+    /// Like `push`, but returns an error instead of panicking when the arena is full
+    #[inline(always)]
+    pub fn try_push(&mut self, item: T) -> Result<(), CapacityError> {
+        self.try_extend_one(item)
+    }
+
+    /// Appends `n` copies of `value`, panicking when the arena is full
+    #[inline(always)]
+    pub fn extend_with(&mut self, n: usize, value: T) {
+        if let Err(e) = self.try_extend_with(n, value) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `extend_with`, but returns an error instead of panicking when the arena is full
+    #[inline(always)]
+    pub fn try_extend_with(&mut self, n: usize, value: T) -> Result<(), CapacityError> {
+        self.alloc.check("LiquidVecRef::try_extend_with");
+        self.alloc.try_ensure(n.saturating_mul(size_of::<T>()))?;
+        for _ in 0..n {
+            unsafe { self.write_one(value) }
+        }
+        Ok(())
+    }
+
+    /// Appends `item` without checking there's room for it
+    #[inline(always)]
+    unsafe fn write_one(&mut self, item: T) {
+        self.end().write(item);
+        self.alloc.top_size += size_of::<T>();
+    }
+
+    /// Makes sure `additional` more elements fit, and hints the OS to back them; panics if they don't fit
+    #[inline(always)]
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(e) = self.try_reserve(additional) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `reserve`, but returns an error instead of panicking when the arena can't hold `additional` more elements
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), CapacityError> {
+        self.alloc.check("LiquidVecRef::try_reserve");
+        let len = additional.saturating_mul(size_of::<T>());
+        self.alloc.try_ensure(len)?;
+        self.alloc.reserved = self.alloc.top_end() + len;
+        unsafe {
+            self.alloc.source.will_need(self.end() as _, len);
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn extend_from_slice(&mut self, items: &[T]) {
+        self.alloc.check("LiquidVecRef::extend_from_slice");
+        self.alloc.ensure(core::mem::size_of_val(items));
+        unsafe { self.write_slice(items) }
+    }
+
+    /// Like `extend_from_slice`, but returns an error instead of panicking when the arena is full
+    #[inline(always)]
+    pub fn try_extend_from_slice(&mut self, items: &[T]) -> Result<(), CapacityError> {
+        self.alloc.check("LiquidVecRef::try_extend_from_slice");
+        self.alloc.try_ensure(core::mem::size_of_val(items))?;
+        unsafe { self.write_slice(items) }
+        Ok(())
+    }
+
+    /// Appends the chunks one after the other, panicking when the arena is full
+    ///
+    /// The iterator is gone through twice: once to add up the room needed, checked in one go, then to copy the
+    /// chunks, with the length updated once at the end. Should the second pass yield more than the first, the copy
+    /// stops at the room that was checked.
+    #[inline(always)]
+    pub fn extend_from_chunks<'a, I>(&mut self, chunks: I) where I: IntoIterator<Item = &'a [T]>, I::IntoIter: Clone, T: 'a {
+        if let Err(e) = self.try_extend_from_chunks(chunks) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `extend_from_chunks`, but returns an error instead of panicking when the arena is full, appending nothing
+    pub fn try_extend_from_chunks<'a, I>(&mut self, chunks: I) -> Result<(), CapacityError>
+    where I: IntoIterator<Item = &'a [T]>, I::IntoIter: Clone, T: 'a {
+        let chunks = chunks.into_iter();
+        self.alloc.check("LiquidVecRef::try_extend_from_chunks");
+        let len = chunks.clone().map(|chunk| chunk.len()).sum::<usize>();
+        self.alloc.try_ensure(len.saturating_mul(size_of::<T>()))?;
+        let end = self.end();
+        let mut written = 0;
+        for chunk in chunks {
+            let n = chunk.len().min(len - written);
+            unsafe { core::ptr::copy(chunk.as_ptr(), end.add(written), n) };
+            written += n;
+        }
+        self.alloc.top_size += written * size_of::<T>();
+        Ok(())
+    }
+
+    /// Appends `items` without checking there's room for them
+    #[inline(always)]
+    unsafe fn write_slice(&mut self, items: &[T]) {
+        core::ptr::copy(items.as_ptr(), self.end(), items.len());
+        self.alloc.top_size += core::mem::size_of_val(items);
+    }
+
+    /// Appends a copy of the elements in `src`, which must lie within the vector, panicking when the arena is full
+    ///
+    /// The source ends at or before the current end, so it never overlaps the copy and is copied as a block. To repeat
+    /// a run that continues into the elements being appended, as LZ77 decoders do, use
+    /// `extend_from_within_overlapping`.
+    #[inline(always)]
+    pub fn extend_from_within<R>(&mut self, src: R) where R : core::slice::SliceIndex<[T], Output = [T]> {
+        unsafe {
+            self.extend_from_slice(&core::slice::from_raw_parts(self.alloc.top_base as *const T, self.len())[src])
+        }
+    }
+
+    /// Like `extend_from_within`, but returns an error instead of panicking when the arena is full
+    #[inline(always)]
+    pub fn try_extend_from_within<R>(&mut self, src: R) -> Result<(), CapacityError> where R : core::slice::SliceIndex<[T], Output = [T]> {
+        unsafe {
+            self.try_extend_from_slice(&core::slice::from_raw_parts(self.alloc.top_base as *const T, self.len())[src])
+        }
+    }
+
+    /// Appends `len` elements, each a copy of the one `self.len() - src` places before it, panicking when the arena is
+    /// full
+    ///
+    /// This is an LZ77 match: when `len` goes past the current end, the elements from `src` on repeat, so a single
+    /// element at `src` is replicated `len` times. Panics if `src` isn't the index of an element, unless `len` is 0.
+    #[inline(always)]
+    pub fn extend_from_within_overlapping(&mut self, src: usize, len: usize) {
+        if let Err(e) = self.try_extend_from_within_overlapping(src, len) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `extend_from_within_overlapping`, but returns an error instead of panicking when the arena is full
+    pub fn try_extend_from_within_overlapping(&mut self, src: usize, len: usize) -> Result<(), CapacityError> {
+        if len == 0 {
+            return Ok(());
+        }
+        assert!(src < self.len(), "extend_from_within_overlapping: source {src} is past the end at {}", self.len());
+        self.alloc.check("LiquidVecRef::try_extend_from_within_overlapping");
+        self.alloc.try_ensure(len.saturating_mul(size_of::<T>()))?;
+        let mut remaining = len;
+        while remaining != 0 {
+            // The run from `src` to the end is a whole number of periods, so copying its start keeps it periodic
+            let chunk = remaining.min(self.len() - src);
+            unsafe {
+                let base = self.alloc.top_base as *const T;
+                core::ptr::copy_nonoverlapping(base.add(src), self.end(), chunk);
+                self.alloc.top_size += chunk * size_of::<T>();
+            }
+            remaining -= chunk;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.alloc.top_size == 0 {
+            None
+        } else {
+            unsafe {
+                let item = core::ptr::read(self.end().sub(1));
+                self.alloc.shrink_top(self.alloc.top_size - size_of::<T>());
+                Some(item)
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn truncate(&mut self, len: usize) {
+        if len > self.len() {
+            return;
+        }
+        self.alloc.shrink_top(len*size_of::<T>())
+    }
+
+    /// Inserts `item` at `index`, moving the elements after it up; panics if `index > len` or the arena is full
+    #[inline(always)]
+    pub fn insert(&mut self, index: usize, item: T) {
+        self.alloc.check("LiquidVecRef::insert");
+        assert!(index <= self.len(), "insert: index {index} is out of bounds for a vector of {} elements", self.len());
+        self.alloc.ensure(size_of::<T>());
+        unsafe { self.insert_unchecked(index, item) }
+    }
+
+    /// Like `insert`, but returns an error instead of panicking when the arena is full
+    #[inline(always)]
+    pub fn try_insert(&mut self, index: usize, item: T) -> Result<(), CapacityError> {
+        self.alloc.check("LiquidVecRef::try_insert");
+        assert!(index <= self.len(), "insert: index {index} is out of bounds for a vector of {} elements", self.len());
+        self.alloc.try_ensure(size_of::<T>())?;
+        unsafe { self.insert_unchecked(index, item) }
+        Ok(())
+    }
+
+    /// Inserts `item` at `index` without checking there's room for it
+    #[inline(always)]
+    unsafe fn insert_unchecked(&mut self, index: usize, item: T) {
+        let at = self.as_mut_ptr().add(index);
+        core::ptr::copy(at, at.add(1), self.len() - index);
+        at.write(item);
+        self.alloc.top_size += size_of::<T>();
+    }
+
+    /// Removes and returns the element at `index`, moving the elements after it down; panics if `index >= len`
+    #[inline(always)]
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len(), "remove: index {index} is out of bounds for a vector of {} elements", self.len());
+        unsafe {
+            let at = self.as_mut_ptr().add(index);
+            let item = at.read();
+            core::ptr::copy(at.add(1), at, self.len() - index - 1);
+            self.alloc.shrink_top(self.alloc.top_size - size_of::<T>());
+            item
+        }
+    }
+
+    /// Removes and returns the element at `index`, putting the last element in its place; panics if `index >= len`
+    #[inline(always)]
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len(), "swap_remove: index {index} is out of bounds for a vector of {} elements", self.len());
+        unsafe {
+            let at = self.as_mut_ptr().add(index);
+            let item = at.read();
+            at.write(self.end().sub(1).read());
+            self.alloc.shrink_top(self.alloc.top_size - size_of::<T>());
+            item
+        }
+    }
+
+    /// Removes the elements in `range`, returning them as an iterator; the rest is moved down when it's dropped
+    ///
+    /// Panics if the range is out of bounds. If the iterator is leaked, the elements after the range are lost too.
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T> {
+        self.alloc.check("LiquidVecRef::drain");
+        let (start, end) = bounds(range, self.len());
+        self.drain_from(start, end)
+    }
+
+    /// Replaces the elements in `range` by those of `replace_with`, returning the removed ones as an iterator
+    ///
+    /// Unlike `Vec::splice`, the new elements go in right away, at the end of the vector and then rotated into place;
+    /// the iterator only closes the gap left by the removed ones when it's dropped. Panics if the range is out of
+    /// bounds or the arena is full.
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> Drain<'_, T>
+    where R: RangeBounds<usize>, I: IntoIterator<Item = T> {
+        self.alloc.check("LiquidVecRef::splice");
+        let len = self.len();
+        let (start, end) = bounds(range, len);
+        self.extend(replace_with);
+        self[end..].rotate_left(len - end);
+        self.drain_from(start, end)
+    }
+
+    /// Drains `start..end`, with everything after it as the tail that's moved down
+    #[inline(always)]
+    fn drain_from(&mut self, start: usize, end: usize) -> Drain<'_, T> {
+        let len = self.len();
+        unsafe {
+            let drained = core::slice::from_raw_parts(self.as_ptr().add(start), end - start);
+            self.alloc.dirty = self.alloc.dirty.max(self.alloc.top_end());
+            self.alloc.top_size = start*size_of::<T>();
+            Drain { iter: drained.iter(), alloc: &mut *self.alloc, tail_start: end, tail_len: len - end }
+        }
+    }
+
+    /// Grows the vector to `new_len` with copies of `value`, or truncates it; panics when the arena is full
+    #[inline(always)]
+    pub fn resize(&mut self, new_len: usize, value: T) {
+        self.resize_with(new_len, || value)
+    }
+
+    /// Like `resize`, but returns an error instead of panicking when the arena is full
+    #[inline(always)]
+    pub fn try_resize(&mut self, new_len: usize, value: T) -> Result<(), CapacityError> {
+        self.try_resize_with(new_len, || value)
+    }
+
+    /// Grows the vector to `new_len` with elements made by `f`, or truncates it; panics when the arena is full
+    #[inline(always)]
+    pub fn resize_with<F: FnMut() -> T>(&mut self, new_len: usize, f: F) {
+        if let Err(e) = self.try_resize_with(new_len, f) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `resize_with`, but returns an error instead of panicking when the arena is full
+    pub fn try_resize_with<F: FnMut() -> T>(&mut self, new_len: usize, mut f: F) -> Result<(), CapacityError> {
+        self.alloc.check("LiquidVecRef::resize_with");
+        let len = self.len();
+        if new_len <= len {
+            self.truncate(new_len);
+            return Ok(());
+        }
+        let additional = (new_len - len).saturating_mul(size_of::<T>());
+        self.alloc.try_ensure(additional)?;
+        for _ in len..new_len {
+            unsafe { self.write_one(f()) }
+        }
+        Ok(())
+    }
+
+    /// Keeps only the elements `keep` returns true for, in order
+    #[inline(always)]
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        self.retain_mut(|item| keep(item))
+    }
+
+    /// Like `retain`, but `keep` may also change the elements
+    ///
+    /// If `keep` panics, the vector is left with the elements kept so far followed by the ones not yet visited,
+    /// starting with the one it panicked on.
+    pub fn retain_mut<F: FnMut(&mut T) -> bool>(&mut self, mut keep: F) {
+        let len = self.len();
+        let mut shift = Backshift { vec: self, visited: 0, kept: 0 };
+        while shift.visited < len {
+            let i = shift.visited;
+            let retained = keep(&mut shift.vec[i]);
+            shift.visited += 1;
+            if retained {
+                let item = shift.vec[i];
+                shift.vec[shift.kept] = item;
+                shift.kept += 1;
+            }
+        }
+    }
+
+    /// Removes consecutive repeated elements
+    #[inline(always)]
+    pub fn dedup(&mut self) where T: PartialEq {
+        self.dedup_by(|a, b| a == b)
+    }
+
+    /// Removes consecutive elements that map to the same key
+    #[inline(always)]
+    pub fn dedup_by_key<K: PartialEq, F: FnMut(&mut T) -> K>(&mut self, mut key: F) {
+        self.dedup_by(|a, b| key(a) == key(b))
+    }
+
+    /// Removes consecutive elements for which `same_bucket(element, previous kept element)` returns true
+    pub fn dedup_by<F: FnMut(&mut T, &mut T) -> bool>(&mut self, mut same_bucket: F) {
+        let len = self.len();
+        if len <= 1 {
+            return;
+        }
+        let items: &mut [T] = self;
+        let mut kept = 1;
+        for i in 1..len {
+            let (head, tail) = items.split_at_mut(i);
+            if !same_bucket(&mut tail[0], &mut head[kept - 1]) {
+                items[kept] = items[i];
+                kept += 1;
+            }
+        }
+        self.alloc.shrink_top(kept*size_of::<T>());
+    }
+
+    /// The committed memory after the vector, to be written before `set_len` takes it in
+    ///
+    /// That's up to the next commit boundary, or further if that much was asked for with `reserve`, which is the way
+    /// to be sure of the room. The arena then can't assume these bytes are still zero.
+    pub fn spare_capacity_mut(&mut self) -> &mut [core::mem::MaybeUninit<T>] {
+        self.alloc.check("LiquidVecRef::spare_capacity_mut");
+        let top_end = self.alloc.top_end();
+        let window = top_end.next_multiple_of(COMMIT_GRANULARITY).max(self.alloc.reserved);
+        let end = window.min(self.alloc.committed);
+        let spare = end.saturating_sub(top_end)/size_of::<T>();
+        self.alloc.dirty = self.alloc.dirty.max(top_end + spare*size_of::<T>());
+        unsafe { core::slice::from_raw_parts_mut(self.end() as *mut core::mem::MaybeUninit<T>, spare) }
+    }
+
+    /// Sets the length of the vector without writing or checking anything
+    ///
+    /// # Safety
+    /// The first `new_len` elements must be initialized, and lie in the committed part of the arena: at most
+    /// `len + spare_capacity_mut().len()`.
+    #[inline(always)]
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= self.len() + (self.alloc.committed - self.alloc.top_end())/size_of::<T>());
+        self.alloc.dirty = self.alloc.dirty.max(self.alloc.top_end());
+        self.alloc.top_size = new_len*size_of::<T>();
+    }
+
+    /// Removes all elements, giving their space back to the arena
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.alloc.shrink_top(0)
+    }
+
+    /// Pointer to the first element; the arena never moves, so it stays valid as the vector grows
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *const T {
+        self.alloc.top_base as *const T
+    }
+
+    /// Mutable pointer to the first element, see `as_ptr`
+    #[inline(always)]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.alloc.top_base as *mut T
+    }
+
+    /// How many elements the vector can hold before the arena's address space runs out, see `remaining`
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.len() + self.remaining()
+    }
+
+    /// How many more elements can be appended before the arena's address space runs out
+    ///
+    /// With `OverflowPolicy::Grow` the arena may be able to grow past that.
+    #[inline(always)]
+    pub fn remaining(&self) -> usize {
+        self.alloc.remaining()/size_of::<T>()
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.alloc.top_size/size_of::<T>()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.alloc.top_size == 0
+    }
+}
+
+impl <'alloc, 'data> LiquidVecRef<'alloc, 'data, u8> {
+    /// Appends `n` zero bytes, panicking when the arena is full
+    #[inline(always)]
+    pub fn extend_zeroed(&mut self, n: usize) {
+        if let Err(e) = self.try_extend_zeroed(n) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `extend_zeroed`, but returns an error instead of panicking when the arena is full
+    ///
+    /// Memory fresh from the OS is already zero, so only the part of the range that was written before is cleared.
+    #[inline(always)]
+    pub fn try_extend_zeroed(&mut self, n: usize) -> Result<(), CapacityError> {
+        self.alloc.check("LiquidVecRef::try_extend_zeroed");
+        self.alloc.try_ensure(n)?;
+        let end = self.alloc.top_end();
+        let dirty = self.alloc.zeroed_from().saturating_sub(end).min(n);
+        unsafe { self.end().write_bytes(0, dirty) };
+        self.alloc.top_size += n;
+        Ok(())
+    }
+
+    /// Appends zero bytes until the end of the vector is at a multiple of `align` bytes, panicking when the arena is full
+    ///
+    /// This shadows `[u8]::align_to`, which can still be called as `(*v).align_to::<U>()`.
+    /// Panics if `align` isn't a power of two.
+    #[inline(always)]
+    pub fn align_to(&mut self, align: usize) {
+        if let Err(e) = self.try_align_to(align) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `align_to`, but returns an error instead of panicking when the arena is full
+    pub fn try_align_to(&mut self, align: usize) -> Result<(), CapacityError> {
+        assert!(align.is_power_of_two(), "align_to: {align} is not a power of two");
+        let padding = self.end().align_offset(align);
+        self.try_extend_zeroed(padding)
+    }
+
+    /// Freeze into a shared slice, or return an earlier slice with the same bytes instead, if the allocator keeps an
+    /// index of them (see `BumpAlloc::with_dedup`)
+    ///
+    /// Only slices frozen with `freeze_dedup` are considered, as they're the only ones that can be shared.
+    pub fn freeze_dedup(self) -> &'data [u8] {
+        self.alloc.check("LiquidVecRef::freeze_dedup");
+        #[cfg(feature = "std")]
+        if let Some(found) = self.alloc.find_duplicate(&self) {
+            let len = self.len();
+            let hash = match found {
+                Ok(start) => {
+                    self.discard();
+                    return unsafe { core::slice::from_raw_parts(start, len) };
+                }
+                Err(hash) => hash,
+            };
+            let offset = unsafe { self.alloc.top_base.offset_from(self.alloc.data_base) as usize };
+            if let Some(dedup) = &mut self.alloc.dedup {
+                dedup.entry(hash).or_default().push((offset, len));
+            }
+        }
+        self.freeze_shared()
+    }
+
+    /// Freezes the bytes as a string, after checking they're valid UTF-8
+    ///
+    /// On error the vector is dropped as if it hadn't been frozen, so the allocator's `DropPolicy` decides what
+    /// happens to the bytes.
+    pub fn freeze_str(self) -> Result<&'data mut str, core::str::Utf8Error> {
+        core::str::from_utf8(&self)?;
+        Ok(unsafe { self.freeze_str_unchecked() })
+    }
+
+    /// Freezes the bytes as a string without checking them
+    ///
+    /// # Safety
+    /// The bytes must be valid UTF-8.
+    #[inline(always)]
+    pub unsafe fn freeze_str_unchecked(self) -> &'data mut str {
+        unsafe { core::str::from_utf8_unchecked_mut(self.freeze()) }
+    }
+
+    /// Appends a NUL terminator and freezes the bytes as a C string
+    ///
+    /// Fails like `CString::new` if the bytes already contain a NUL, without appending the terminator; the vector is
+    /// then dropped as if it hadn't been frozen, so the allocator's `DropPolicy` decides what happens to the bytes.
+    pub fn freeze_cstr(mut self) -> Result<&'data core::ffi::CStr, NulError> {
+        if let Some(position) = self.iter().position(|&b| b == 0) {
+            return Err(NulError { position });
+        }
+        self.push(0);
+        Ok(unsafe { core::ffi::CStr::from_bytes_with_nul_unchecked(self.freeze_shared()) })
+    }
+}
+
+/// The bytes given to `LiquidVecRef::freeze_cstr` contain a NUL, so they can't be a C string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NulError {
+    position: usize,
+}
+
+impl NulError {
+    /// The offset of the first NUL byte
+    pub fn nul_position(&self) -> usize {
+        self.position
+    }
+}
+
+impl core::fmt::Display for NulError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "nul byte found in provided data at position: {}", self.position)
+    }
+}
+
+impl core::error::Error for NulError {}
+
+/// Resolves `range` into `start..end` within `len` elements, panicking if it doesn't fit
+fn bounds<R: RangeBounds<usize>>(range: R, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&n) => n,
+        Bound::Excluded(&n) => n.checked_add(1).expect("range start overflows"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&n) => n.checked_add(1).expect("range end overflows"),
+        Bound::Excluded(&n) => n,
+        Bound::Unbounded => len,
+    };
+    assert!(start <= end && end <= len, "range {start}..{end} is out of bounds for a vector of {len} elements");
+    (start, end)
+}
+
+/// The elements removed by `LiquidVecRef::drain` or `LiquidVecRef::splice`
+pub struct Drain<'v, T: Copy> {
+    iter: core::slice::Iter<'v, T>,
+    alloc: &'v mut BumpAlloc<dyn PageSource>,
+    tail_start: usize,
+    tail_len: usize,
+}
+
+impl <'v, T: Copy> Iterator for Drain<'v, T> {
+    type Item = T;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<T> {
+        self.iter.next().copied()
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl <'v, T: Copy> DoubleEndedIterator for Drain<'v, T> {
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back().copied()
+    }
+}
+
+impl <'v, T: Copy> ExactSizeIterator for Drain<'v, T> {}
+
+/// Moves the elements `retain_mut` hasn't visited down to the ones it kept, also when unwinding from `keep`
+struct Backshift<'v, 'alloc, 'data, T: Copy> {
+    vec: &'v mut LiquidVecRef<'alloc, 'data, T>,
+    visited: usize,
+    kept: usize,
+}
+
+impl <'v, 'alloc, 'data, T: Copy> Drop for Backshift<'v, 'alloc, 'data, T> {
+    fn drop(&mut self) {
+        let rest = self.vec.len() - self.visited;
+        unsafe {
+            let base = self.vec.as_mut_ptr();
+            core::ptr::copy(base.add(self.visited), base.add(self.kept), rest);
+        }
+        self.vec.alloc.shrink_top((self.kept + rest)*size_of::<T>());
+    }
+}
+
+impl <'v, T: Copy> Drop for Drain<'v, T> {
+    /// Moves the elements after the drained range down to close the gap
+    fn drop(&mut self) {
+        let size = size_of::<T>();
+        let start = self.alloc.top_size;
+        unsafe {
+            let base = self.alloc.top_base;
+            core::ptr::copy(base.add(self.tail_start*size), base.add(start), self.tail_len*size);
+        }
+        self.alloc.top_size = (self.tail_start + self.tail_len)*size;
+        self.alloc.shrink_top(start + self.tail_len*size);
+    }
+}
+
+impl <'alloc, 'data, T: Copy> core::borrow::Borrow<[T]> for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn borrow(&self) -> &[T] {
+        self.deref()
+    }
+}
+
+impl <'alloc, 'data, T: Copy> core::borrow::BorrowMut<[T]> for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self.deref_mut()
+    }
+}
+
+impl <'alloc, 'data, T: Copy> AsRef<[T]> for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn as_ref(&self) -> &[T] {
+        self.deref()
+    }
+}
+
+impl <'alloc, 'data, T: Copy> AsMut<[T]> for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn as_mut(&mut self) -> &mut [T] {
+        self.deref_mut()
+    }
+}
+
+impl <'a, 'alloc, 'data, T: Copy> IntoIterator for &'a LiquidVecRef<'alloc, 'data, T> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.deref().iter()
+    }
+}
+
+impl <'a, 'alloc, 'data, T: Copy> IntoIterator for &'a mut LiquidVecRef<'alloc, 'data, T> {
+    type Item = &'a mut T;
+    type IntoIter = core::slice::IterMut<'a, T>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.deref_mut().iter_mut()
+    }
+}
+
+impl <'alloc, 'data, T: Copy> Extend<T> for LiquidVecRef<'alloc, 'data, T>  {
+    #[inline(always)]
+    fn extend<I: IntoIterator<Item=T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|b| self.extend_one(b))
+    }
+}
+
+impl <'alloc, 'data, T: Copy, I: SliceIndex<[T]>> core::ops::Index<I> for LiquidVecRef<'alloc, 'data, T>  {
+    type Output = I::Output;
+    #[inline(always)]
+    fn index(&self, index: I) -> &Self::Output { core::ops::Index::index(self.deref(), index) }
+}
+
+impl <'alloc, 'data, T: Copy, I: SliceIndex<[T]>> core::ops::IndexMut<I> for LiquidVecRef<'alloc, 'data, T>  {
+    #[inline(always)]
+    fn index_mut(&mut self, index: I) -> &mut Self::Output { core::ops::IndexMut::index_mut(self.deref_mut(), index) }
+}
+
+impl <'alloc, 'data, T: Copy> core::ops::Deref for LiquidVecRef<'alloc, 'data, T> {
+    type Target = [T];
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe {
+            core::slice::from_raw_parts(self.alloc.top_base as *const T, self.len())
+        }
+    }
+}
+
+impl <'alloc, 'data, T: Copy> core::ops::DerefMut for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe {
+            core::slice::from_raw_parts_mut(self.alloc.top_base as *mut T, self.len())
+        }
+    }
+}
+
+/// Writes append to the vector; running out of arena is reported as `ErrorKind::OutOfMemory`, with nothing written
+#[cfg(feature = "std")]
+impl <'alloc, 'data> std::io::Write for LiquidVecRef<'alloc, 'data, u8> {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> std::io::Result<usize> {
+        self.try_extend_from_slices(bufs).map_err(|e| std::io::Error::new(std::io::ErrorKind::OutOfMemory, e))?;
+        Ok(bufs.iter().map(|buf| buf.len()).sum())
+    }
+
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.try_extend_from_slice(buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::OutOfMemory, e))
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Formatting appends to the vector; running out of arena is reported as `fmt::Error`, see `freeze_format!`
+impl <'alloc, 'data> core::fmt::Write for LiquidVecRef<'alloc, 'data, u8> {
+    #[inline(always)]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.try_extend_from_slice(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+impl <'alloc, 'data, T: Copy + core::fmt::Debug> core::fmt::Debug for LiquidVecRef<'alloc, 'data, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl <'alloc, 'data, T: Copy + PartialEq> PartialEq for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.deref() == other.deref()
+    }
+}
+
+impl <'alloc, 'data, T: Copy + PartialEq> PartialEq<[T]> for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn eq(&self, other: &[T]) -> bool {
+        self.deref() == other
+    }
+}
+
+impl <'alloc, 'data, T: Copy + PartialEq> PartialEq<&[T]> for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn eq(&self, other: &&[T]) -> bool {
+        self.deref() == *other
+    }
+}
+
+impl <'alloc, 'data, T: Copy + PartialEq, const N: usize> PartialEq<[T; N]> for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn eq(&self, other: &[T; N]) -> bool {
+        self.deref() == other
+    }
+}
+
+impl <'alloc, 'data, T: Copy + Eq> Eq for LiquidVecRef<'alloc, 'data, T> {}
+
+impl <'alloc, 'data, T: Copy + core::hash::Hash> core::hash::Hash for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.deref().hash(state)
+    }
+}
+
+impl <'alloc, 'data, T: Copy + PartialOrd> PartialOrd for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        self.deref().partial_cmp(other.deref())
+    }
+}
+
+impl <'alloc, 'data, T: Copy + Ord> Ord for LiquidVecRef<'alloc, 'data, T> {
+    #[inline(always)]
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.deref().cmp(other.deref())
+    }
+}
+
+impl <'alloc, 'data, T> Drop for LiquidVecRef<'alloc, 'data, T> {
+    /// Applies the allocator's `DropPolicy` to a vector that wasn't frozen
+    #[inline(always)]
+    fn drop(&mut self) {
+        if self.alloc.top_size != 0 {
+            self.alloc.drop_top()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{BumpAlloc, DropPolicy};
+
+    #[test]
+    fn basis() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+
+        let s1: &mut [u8] = {
+            let mut v1 = alloc.top();
+            v1.extend_from_slice(&[1, 2, 3]);
+            v1.extend_one(4);
+            v1.extend_from_within(..3);
+            v1.deref_mut().reverse();
+            v1.pop();
+            v1.freeze()
+        };
+
+        assert_eq!(s1, [3, 2, 1, 4, 3, 2]);
+
+        let s2: &mut [u8] = {
+            let mut v1 = alloc.top();
+            v1.extend_from_slice(&[10, 20, 30]);
+            v1.extend_one(40);
+            v1.extend_from_within(..3);
+            v1.deref_mut().reverse();
+            v1.pop();
+            v1.freeze()
+        };
+
+        assert_eq!(s2, [30, 20, 10, 40, 30, 20]);
+        assert_eq!(alloc.data_size(), (s1.len() + s2.len()));
+    }
+
+    #[test]
+    fn drop_policy() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let mut keep = BumpAlloc::new();
+        let mut keep = keep.to_ref();
+        keep.top().extend_from_slice(&[1, 2]);
+        keep.top().extend_from_slice(&[3]);
+        assert_eq!(keep.top().freeze(), [1, 2, 3]);
+
+        let mut rollback = BumpAlloc::new()
+            .with_drop_policy(DropPolicy::Rollback)
+            .with_drop_hook(|n| { DROPPED.fetch_add(n, Ordering::Relaxed); });
+        let mut rollback = rollback.to_ref();
+        rollback.top().extend_from_slice(&[1, 2]);
+        let mut v = rollback.top();
+        v.extend_from_slice(&[3]);
+        assert_eq!(v.freeze(), [3]);
+        assert_eq!(rollback.data_size(), 1);
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+
+        let mut freeze = BumpAlloc::new().with_drop_policy(DropPolicy::Freeze);
+        let mut freeze = freeze.to_ref();
+        freeze.top().extend_from_slice(&[1, 2]);
+        assert!(freeze.top().is_empty());
+        assert_eq!(freeze.data_size(), 2);
+    }
+
+    #[test]
+    fn discard() {
+        let mut alloc = BumpAlloc::new().with_drop_hook(|_| panic!("discarded vector was dropped"));
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(v1.freeze(), [1, 2, 3]);
+        let mut v2 = alloc.top();
+        v2.extend_from_slice(&[4, 5]);
+        v2.discard();
+        assert_eq!(alloc.data_size(), 3);
+        let mut v3 = alloc.top();
+        v3.extend_one(6);
+        assert_eq!(v3.freeze(), [6]);
+    }
+
+    #[test]
+    fn freeze_str() {
+        let mut alloc = BumpAlloc::new().with_drop_policy(DropPolicy::Rollback);
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice("héllo".as_bytes());
+        let s1 = v1.freeze_str().unwrap();
+        s1.make_ascii_uppercase();
+        assert_eq!(s1, "HéLLO");
+        let mut v2 = alloc.top();
+        v2.extend_from_slice(&[b'a', 0xC3]);
+        assert_eq!(v2.freeze_str().unwrap_err().valid_up_to(), 1);
+        assert_eq!(alloc.data_size(), 6);
+    }
+
+    #[test]
+    fn aligned() {
+        let mut alloc = BumpAlloc::new().with_canaries();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(&[1, 2, 3]);
+        v1.align_to(16);
+        assert_eq!(v1.len(), 16);
+        assert_eq!(v1.freeze_aligned(64)[..4], [1, 2, 3, 0]);
+        let mut v2 = alloc.top();
+        v2.push(4);
+        assert_eq!(v2.as_ptr() as usize % 64, 0);
+        v2.align_to(8);
+        let (head, words, _) = unsafe { v2.freeze().align_to_mut::<u64>() };
+        assert_eq!((head.len(), &*words), (0, &[u64::from_le(4)][..]));
+    }
+
+    #[test]
+    fn freeze_cstr() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"hello");
+        assert_eq!(v1.freeze_cstr().unwrap(), c"hello");
+        let mut v2 = alloc.top();
+        v2.extend_from_slice(b"a\0b");
+        assert_eq!(v2.freeze_cstr().unwrap_err().nul_position(), 1);
+        assert_eq!(alloc.top(), *b"a\0b");
+    }
+
+    #[test]
+    fn std_traits() {
+        use std::hash::{BuildHasher, RandomState};
+        let (mut alloc1, mut alloc2) = (BumpAlloc::new(), BumpAlloc::new());
+        let (mut alloc1, mut alloc2) = (alloc1.to_ref(), alloc2.to_ref());
+        let mut v1 = alloc1.top();
+        let mut v2 = alloc2.top();
+        v1.extend_from_slice(b"abc");
+        v2.extend_from_slice(b"abd");
+        assert_eq!(v1, *b"abc");
+        assert_eq!(v1, &b"abc"[..]);
+        assert_ne!(v1, v2);
+        assert!(v1 < v2);
+        assert_eq!(format!("{v1:?}"), "[97, 98, 99]");
+        let state = RandomState::new();
+        v2.pop();
+        v2.extend_one(b'c');
+        assert_eq!(v1, v2);
+        assert_eq!(state.hash_one(&v1), state.hash_one(&b"abc"[..]));
+    }
+
+    #[test]
+    fn views() {
+        fn checksum(bytes: impl AsRef<[u8]>) -> u32 {
+            bytes.as_ref().iter().map(|&b| b as u32).sum()
+        }
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(&[1, 2, 3]);
+        for b in &mut v1 {
+            *b *= 2;
+        }
+        v1.as_mut()[0] = 0;
+        assert_eq!(checksum(&v1), 10);
+        assert_eq!((&v1).into_iter().copied().max(), Some(6));
+    }
+
+    #[test]
+    fn io_write() {
+        use std::io::{IoSlice, Write};
+        let mut alloc = BumpAlloc::from_boxed_buffer(vec![0; 16].into_boxed_slice());
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        write!(v1, "{}-ab", 12).unwrap();
+        assert_eq!(v1.write_vectored(&[IoSlice::new(b"cd"), IoSlice::new(b"ef")]).unwrap(), 4);
+        v1.flush().unwrap();
+        assert_eq!(v1, *b"12-abcdef");
+        let err = v1.write(&[0; 8]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::OutOfMemory);
+        assert_eq!(v1.freeze(), b"12-abcdef");
+    }
+
+    #[test]
+    fn vec_basics() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top_of::<u16>();
+        assert!(v1.is_empty());
+        v1.push(1);
+        v1.try_push(2).unwrap();
+        unsafe { *v1.as_mut_ptr().add(1) = 3 };
+        assert_eq!(unsafe { *v1.as_ptr() }, 1);
+        assert_eq!(v1, [1, 3]);
+        v1.clear();
+        assert!(v1.is_empty());
+        v1.push(4);
+        assert_eq!(v1.freeze(), [4]);
+        assert_eq!(alloc.data_size(), 2);
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top_of::<u32>();
+        v1.extend_from_slice(&[1, 2, 3]);
+        v1.insert(0, 0);
+        v1.insert(4, 4);
+        v1.try_insert(2, 9).unwrap();
+        assert_eq!(v1, [0, 1, 9, 2, 3, 4]);
+        assert_eq!(v1.remove(2), 9);
+        assert_eq!(v1.swap_remove(0), 0);
+        assert_eq!(v1.remove(3), 3);
+        assert_eq!(v1.freeze(), [4, 1, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn remove_out_of_bounds() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.push(1);
+        v1.remove(1);
+    }
+
+    #[test]
+    fn drain() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"frame1frame2rest");
+        assert_eq!(v1.drain(..6).collect::<Vec<_>>(), b"frame1");
+        assert_eq!(v1.drain(1..=4).rev().collect::<Vec<_>>(), b"emar");
+        let mut partial = v1.drain(2..);
+        assert_eq!((partial.len(), partial.next()), (4, Some(b'r')));
+        drop(partial);
+        v1.extend_from_slice(b"!");
+        assert_eq!(v1.freeze(), b"f2!");
+        assert_eq!(alloc.data_size(), 3);
+    }
+
+    #[test]
+    fn retain() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"a\x01b\x02c");
+        v1.retain(|b| !b.is_ascii_control());
+        assert_eq!(v1, *b"abc");
+        v1.retain_mut(|b| {
+            b.make_ascii_uppercase();
+            *b != b'B'
+        });
+        assert_eq!(v1.freeze(), b"AC");
+        assert_eq!(alloc.data_size(), 2);
+
+        let mut v2 = alloc.top();
+        v2.extend_from_slice(b"a1b2c3");
+        let panicked = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+            v2.retain(|&b| if b == b'c' { panic!() } else { b.is_ascii_alphabetic() })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(v2, *b"abc3");
+    }
+
+    #[test]
+    fn resize() {
+        let mut alloc = BumpAlloc::from_boxed_buffer(vec![0; 32].into_boxed_slice());
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top_of::<u16>();
+        v1.resize(3, 7);
+        let mut next = 0;
+        v1.resize_with(5, || { next += 1; next });
+        assert_eq!(v1, [7, 7, 7, 1, 2]);
+        v1.resize(2, 0);
+        assert_eq!(v1, [7, 7]);
+        assert_eq!(v1.try_resize(17, 0).unwrap_err().requested(), 30);
+        assert!(v1.try_resize(usize::MAX, 0).is_err());
+        v1.fill(9);
+        assert_eq!(v1.freeze(), [9, 9]);
+    }
+
+    #[test]
+    fn dedup() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"aaabccdaA");
+        v1.dedup();
+        assert_eq!(v1, *b"abcdaA");
+        v1.dedup_by_key(|b| b.to_ascii_lowercase());
+        assert_eq!(v1, *b"abcda");
+        v1.dedup_by(|b, prev| b.abs_diff(*prev) == 1);
+        assert_eq!(v1.freeze(), b"aca");
+    }
+
+    #[test]
+    fn split_off() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"HDRbody");
+        let body = v1.split_off(3);
+        assert_eq!(v1, *b"HDR");
+        v1.extend_from_slice(b"+");
+        let header = v1.freeze();
+        assert_eq!((&*header, &*body), (&b"HDR+"[..], &b"body"[..]));
+        assert_eq!(alloc.data_size(), 8);
+    }
+
+    #[test]
+    fn splice() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"Hello {name}, {greeting}!");
+        let removed: Vec<u8> = v1.splice(6..12, *b"arena").collect();
+        assert_eq!(removed, b"{name}");
+        v1.splice(13..23, b"bye".iter().copied());
+        assert_eq!(v1, *b"Hello arena, bye!");
+        drop(v1.splice(..0, *b"> "));
+        assert_eq!(v1.freeze(), b"> Hello arena, bye!");
+    }
+
+    #[test]
+    fn extend_with() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_with(3, 0xFF);
+        v1.truncate(1);
+        v1.extend_zeroed(4);
+        v1.extend_zeroed(1 << 20);
+        assert_eq!(v1[..5], [0xFF, 0, 0, 0, 0]);
+        assert!(v1[5..].iter().all(|&b| b == 0));
+        assert_eq!(v1.freeze().len(), 5 + (1 << 20));
+
+        let mut buffer = BumpAlloc::from_boxed_buffer(vec![0xAA; 8].into_boxed_slice());
+        let mut buffer = buffer.to_ref();
+        let mut v2 = buffer.top_of::<u16>();
+        v2.extend_with(2, 7);
+        assert_eq!(v2, [7, 7]);
+        v2.freeze();
+        let mut v3 = buffer.top();
+        v3.extend_zeroed(4);
+        assert_eq!(v3.freeze(), [0; 4]);
+    }
+
+    #[test]
+    fn extend_zeroed_after_shrinking() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(&[7; 4]);
+        unsafe { v1.set_len(0) };
+        v1.extend_zeroed(4);
+        assert_eq!(v1, [0; 4]);
+        v1.fill(7);
+        core::mem::forget(v1.drain(1..));
+        v1.extend_zeroed(3);
+        assert_eq!(v1, [7, 0, 0, 0]);
+    }
+
+    #[test]
+    fn spare_capacity() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"> ");
+        let spare = v1.spare_capacity_mut();
+        assert!(spare.len() >= 5);
+        for (dst, &src) in spare.iter_mut().zip(b"input") {
+            dst.write(src);
+        }
+        unsafe { v1.set_len(v1.len() + 5) };
+        assert_eq!(v1, *b"> input");
+        v1.extend_zeroed(2);
+        assert_eq!(v1.freeze(), b"> input\0\0");
+
+        let mut v2 = alloc.top();
+        assert_eq!(v2.spare_capacity_mut().len(), (1 << 16) - 9);
+        v2.reserve(1 << 20);
+        assert_eq!(v2.spare_capacity_mut().len(), 1 << 20);
+        assert_eq!(v2.alloc.dirty, 9 + (1 << 20));
+    }
+
+    #[test]
+    fn reserve() {
+        let mut alloc = BumpAlloc::from_boxed_buffer(vec![0; 64].into_boxed_slice());
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top_of::<u32>();
+        v1.reserve(16);
+        let err = v1.try_reserve(17).unwrap_err();
+        assert_eq!((err.requested(), err.remaining()), (68, 64));
+        assert!(v1.try_reserve(usize::MAX).is_err());
+        v1.extend_with(16, 1);
+        assert_eq!(v1.freeze().len(), 16);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut alloc = BumpAlloc::from_boxed_buffer(vec![0; 64].into_boxed_slice());
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_with(6, 0);
+        v1.freeze();
+        assert_eq!(alloc.remaining(), 58);
+        let mut v2 = alloc.top_of::<u32>();
+        v2.push(1);
+        assert_eq!((v2.len(), v2.remaining(), v2.capacity()), (1, 13, 14));
+    }
+
+    #[test]
+    fn freeze_shared() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"shared");
+        let s1 = v1.freeze_shared();
+        let (a, b) = (s1, s1);
+        let mut v2 = alloc.top();
+        v2.extend_from_slice(a);
+        v2.freeze_mut().make_ascii_uppercase();
+        assert_eq!(b, b"shared");
+        assert_eq!(alloc.data_size(), 12);
+    }
+
+    #[test]
+    fn split_to() {
+        for canaries in [false, true] {
+            let mut alloc = if canaries { BumpAlloc::new().with_canaries() } else { BumpAlloc::new() };
+            let mut alloc = alloc.to_ref();
+            let mut v1 = alloc.top_of::<u32>();
+            v1.extend_from_slice(&[1, 2, 3, 4, 5]);
+            let s1 = v1.split_to(2);
+            assert_eq!(v1[..], [3, 4, 5]);
+            v1.extend_one(6);
+            let s2 = v1.split_to(4);
+            assert!(v1.is_empty());
+            v1.extend_one(7);
+            let s3 = v1.freeze();
+            assert_eq!((&s1[..], &s2[..], &s3[..]), (&[1, 2][..], &[3, 4, 5, 6][..], &[7][..]));
+        }
+    }
+
+    #[test]
+    fn overlapping() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"xab");
+        v1.extend_from_within_overlapping(1, 7);
+        v1.extend_from_within_overlapping(0, 1);
+        v1.extend_from_within_overlapping(10, 4);
+        v1.extend_from_within_overlapping(20, 0);
+        assert_eq!(v1.freeze(), b"xababababaxxxxx");
+
+        let mut v2 = alloc.top_of::<u32>();
+        v2.extend_from_slice(&[1, 2, 3]);
+        v2.extend_from_within_overlapping(0, 1000);
+        assert!(v2.iter().enumerate().all(|(i, &n)| n as usize == i % 3 + 1));
+        assert_eq!(v2.len(), 1003);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn poison() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top_of::<u16>();
+        v1.extend_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(v1.pop(), Some(4));
+        v1.truncate(1);
+        let released = unsafe { core::slice::from_raw_parts(v1.alloc.top_base.add(2), 6) };
+        assert_eq!(released, [0xDD; 6]);
+        assert_eq!(v1.freeze(), [1]);
+    }
+
+    #[test]
+    fn typed() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        #[repr(C)]
+        struct Point { x: u64, y: u64 }
+        unsafe impl crate::Pod for Point {}
+
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+
+        let s1: &mut [u8] = {
+            let mut v1 = alloc.top();
+            v1.extend_from_slice(&[1, 2, 3]);
+            v1.freeze()
+        };
+
+        let s2: &mut [u32] = {
+            let mut v2 = alloc.top_of::<u32>();
+            v2.extend_from_slice(&[10, 20]);
+            v2.extend_one(u32::MAX);
+            v2.extend_from_within(1..);
+            assert_eq!(v2.pop(), Some(u32::MAX));
+            assert_eq!(v2.len(), 4);
+            v2.freeze()
+        };
+
+        let s3: &mut [Point] = {
+            let mut v3 = alloc.top_of::<Point>();
+            v3.extend([Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]);
+            v3[1].x = 5;
+            v3.freeze()
+        };
+
+        assert_eq!(s1, [1, 2, 3]);
+        assert_eq!(s2, [10, 20, u32::MAX, 20]);
+        assert_eq!(s3, [Point { x: 1, y: 2 }, Point { x: 5, y: 4 }]);
+        assert_eq!(s2.as_ptr() as usize % align_of::<u32>(), 0);
+        assert_eq!(s3.as_ptr() as usize % align_of::<Point>(), 0);
+    }
+}
@@ -0,0 +1,130 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use crate::{BumpAlloc, DefaultPages, LiquidVecRef, PageSource};
+
+/// The arena behind an `OwnedAlloc`, shared with the `OwnedFrozen` slices that keep it alive
+struct ArenaCell<S: PageSource>(UnsafeCell<BumpAlloc<S>>);
+
+// Only the `OwnedAlloc` touches the arena while it's alive, the frozen slices just read bytes it no longer writes.
+unsafe impl<S: PageSource + Send> Send for ArenaCell<S> {}
+unsafe impl<S: PageSource + Send> Sync for ArenaCell<S> {}
+
+/// A `BumpAlloc` whose frozen slices own a share of it, see `BumpAlloc::into_owned`
+///
+/// The memory is released once the `OwnedAlloc` and every `OwnedFrozen` frozen from it are dropped.
+pub struct OwnedAlloc<S: PageSource + Send + 'static = DefaultPages> {
+    arena: Arc<ArenaCell<S>>,
+}
+
+impl<S: PageSource + Send + 'static> BumpAlloc<S> {
+    /// Moves the allocator behind a reference count, so its top vector can be frozen into `'static` slices
+    ///
+    /// The values put in the arena with `BumpAllocRef::alloc_with_drop` are dropped first, as the arena may be
+    /// released on another thread.
+    pub fn into_owned(mut self) -> OwnedAlloc<S> {
+        self.drop_values(self.data_base);
+        OwnedAlloc { arena: Arc::new(ArenaCell(UnsafeCell::new(self))) }
+    }
+}
+
+impl<S: PageSource + Send + 'static> OwnedAlloc<S> {
+    /// Gets the top vector, which `OwnedTop::freeze_owned` turns into an `OwnedFrozen`
+    pub fn top(&mut self) -> OwnedTop<'_, S> {
+        let alloc: &mut BumpAlloc<dyn PageSource> = unsafe { &mut *self.arena.0.get() };
+        OwnedTop {
+            vec: LiquidVecRef { alloc, _data: PhantomData },
+            arena: &self.arena,
+        }
+    }
+
+    /// The total number of data bytes allocated over the lifetime of the allocator
+    pub fn data_size(&self) -> usize {
+        unsafe { (*self.arena.0.get()).top_end() }
+    }
+}
+
+/// The top vector of an `OwnedAlloc`, a `LiquidVecRef` that can also freeze into an `OwnedFrozen`
+pub struct OwnedTop<'a, S: PageSource + Send + 'static = DefaultPages> {
+    vec: LiquidVecRef<'a, 'a>,
+    arena: &'a Arc<ArenaCell<S>>,
+}
+
+impl<'a, S: PageSource + Send + 'static> OwnedTop<'a, S> {
+    /// Freezes the vector into a slice that keeps the arena alive on its own
+    pub fn freeze_owned(self) -> OwnedFrozen {
+        let OwnedTop { vec, arena } = self;
+        let bytes = vec.freeze_shared();
+        OwnedFrozen { ptr: bytes.as_ptr(), len: bytes.len(), _arena: arena.clone() }
+    }
+}
+
+impl<'a, S: PageSource + Send + 'static> Deref for OwnedTop<'a, S> {
+    type Target = LiquidVecRef<'a, 'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.vec
+    }
+}
+
+impl<'a, S: PageSource + Send + 'static> DerefMut for OwnedTop<'a, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.vec
+    }
+}
+
+/// A frozen slice that owns a share of its arena, so it's `'static` and can be sent across threads
+#[derive(Clone)]
+pub struct OwnedFrozen {
+    ptr: *const u8,
+    len: usize,
+    _arena: Arc<dyn core::any::Any + Send + Sync>,
+}
+
+// The bytes are never written again, and the arena they're in is kept alive by `_arena`.
+unsafe impl Send for OwnedFrozen {}
+unsafe impl Sync for OwnedFrozen {}
+
+impl Deref for OwnedFrozen {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl AsRef<[u8]> for OwnedFrozen {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl core::fmt::Debug for OwnedFrozen {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("OwnedFrozen").field(&self.deref()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outlives_alloc() {
+        let mut alloc = BumpAlloc::new().into_owned();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(b"hello");
+        let s1 = v1.freeze_owned();
+        let mut v2 = alloc.top();
+        v2.extend_from_slice(b"world");
+        let s2 = v2.freeze_owned();
+        assert_eq!(alloc.data_size(), 10);
+        drop(alloc);
+
+        let s1_clone = s1.clone();
+        let joined = std::thread::spawn(move || [&s1[..], &s2[..]].concat()).join().unwrap();
+        assert_eq!(joined, b"helloworld");
+        assert_eq!(&*s1_clone, b"hello");
+    }
+}
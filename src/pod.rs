@@ -0,0 +1,66 @@
+use crate::LiquidVecRef;
+
+/// Types for which any bytes of the right size and alignment are a valid value, so frozen bytes can be cast to them
+///
+/// # Safety
+/// The type must be `Copy`, have no padding, and every bit pattern must be a valid value: plain integers, floats,
+/// and `#[repr(C)]` structs or arrays made of them. `bool`, `char`, references and enums are not `Pod`.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! pod {
+    ($($t:ty),*) => { $(unsafe impl Pod for $t {})* };
+}
+
+pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+impl<'alloc, 'data> LiquidVecRef<'alloc, 'data, u8> {
+    /// Freezes the bytes and reinterprets them as a slice of `T`
+    ///
+    /// Panics if the length isn't a multiple of `size_of::<T>()` or the start isn't aligned for `T`. Calling
+    /// `BumpAllocRef::top_of::<T>` while the top vector is empty, or freezing the previous allocation with
+    /// `freeze_aligned`, makes sure the start is aligned.
+    pub fn freeze_as<T: Pod>(self) -> &'data mut [T] {
+        const { assert!(size_of::<T>() != 0, "zero-sized elements are not supported") }
+        assert!(self.len().is_multiple_of(size_of::<T>()), "freeze_as: {} bytes is not a whole number of `{}`",
+                self.len(), core::any::type_name::<T>());
+        assert!(self.as_ptr().cast::<T>().is_aligned(), "freeze_as: the bytes are not aligned for `{}`",
+                core::any::type_name::<T>());
+        let bytes = self.freeze();
+        unsafe { core::slice::from_raw_parts_mut(bytes.as_mut_ptr().cast::<T>(), bytes.len() / size_of::<T>()) }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::BumpAlloc;
+
+    #[test]
+    fn freeze_as() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        #[repr(C)]
+        struct Entry { key: u32, value: f32 }
+        unsafe impl super::Pod for Entry {}
+
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        for n in [7u32, 0.5f32.to_bits(), 8, 1.5f32.to_bits()] {
+            v1.extend_from_slice(&n.to_ne_bytes());
+        }
+        let entries = v1.freeze_as::<Entry>();
+        entries[0].value *= 2.0;
+        assert_eq!(entries, [Entry { key: 7, value: 1.0 }, Entry { key: 8, value: 1.5 }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "3 bytes is not a whole number of `u16`")]
+    fn freeze_as_len() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut v1 = alloc.top();
+        v1.extend_from_slice(&[1, 2, 3]);
+        v1.freeze_as::<u16>();
+    }
+}
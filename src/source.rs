@@ -0,0 +1,165 @@
+/// Where a `BumpAlloc` gets its memory from
+///
+/// A source hands out a single contiguous reservation, which the arena fills front to back.
+/// Depending on the source, reserved memory may need to be committed before it can be touched;
+/// the arena commits ahead of the top vector and decommits when it gives memory back.
+/// Errors are reported as OS error codes (`errno`, or `GetLastError` on Windows), or 0 if there's none.
+///
+/// # Safety
+/// `reserve` must return a pointer to `size` bytes that stay reserved for this source until `release`,
+/// and whose committed parts are readable and writable.
+pub unsafe trait PageSource {
+    /// Reserves `size` bytes of contiguous address space
+    fn reserve(&mut self, size: usize) -> Result<*mut u8, i32>;
+
+    /// Whether `reserve` returns memory that can be written without committing it first
+    fn commits_on_reserve(&self) -> bool {
+        false
+    }
+
+    /// Whether the reserved memory reads as zero until it's first written, and again after `decommit`, so zeroes don't
+    /// have to be written
+    fn zeroed(&self) -> bool {
+        false
+    }
+
+    /// Makes `[ptr, ptr + len)`, part of the reservation, readable and writable
+    ///
+    /// # Safety
+    /// The range must lie in a reservation made by this source.
+    unsafe fn commit(&mut self, ptr: *mut u8, len: usize) -> Result<(), i32>;
+
+    /// Tells the source `[ptr, ptr + len)` doesn't hold data anymore, so its memory can be given back
+    ///
+    /// The range stays reserved and is committed again before it's reused.
+    ///
+    /// # Safety
+    /// The range must lie in a reservation made by this source, and nothing may point into it.
+    unsafe fn decommit(&mut self, ptr: *mut u8, len: usize);
+
+    /// Gives the reservation starting at `ptr` back
+    ///
+    /// # Safety
+    /// `ptr` and `size` must be those of a reservation made by this source, which is never used again.
+    unsafe fn release(&mut self, ptr: *mut u8, size: usize);
+
+    /// Extends the reservation `[ptr, ptr + size)` in place by `additional` bytes, if the address space after it is free
+    ///
+    /// Only used with `OverflowPolicy::Grow`; `release` is then called with the grown size. By default sources can't grow.
+    ///
+    /// # Safety
+    /// `ptr` and `size` must be those of a reservation made by this source.
+    unsafe fn grow(&mut self, _ptr: *mut u8, _size: usize, _additional: usize) -> Result<(), i32> {
+        Err(0)
+    }
+
+    /// Hints that the committed range `[ptr, ptr + len)` is about to be written
+    ///
+    /// # Safety
+    /// The range must lie in a reservation made by this source.
+    unsafe fn will_need(&mut self, _ptr: *mut u8, _len: usize) {}
+
+    /// How many bytes of `[ptr, ptr + len)` are backed by physical memory right now, if the source can tell
+    ///
+    /// # Safety
+    /// The range must lie in a reservation made by this source, and start on a page boundary.
+    unsafe fn resident(&self, _ptr: *mut u8, _len: usize) -> Option<usize> {
+        None
+    }
+}
+
+/// A fixed region of memory handed to the arena up front, see `BumpAlloc::from_buffer`
+///
+/// The arena can't grow past the end of the buffer, writing beyond it panics.
+#[derive(Debug)]
+pub struct FixedBuffer {
+    ptr: *mut u8,
+    len: usize,
+    #[cfg(feature = "std")]
+    owned: bool,
+}
+
+impl FixedBuffer {
+    pub(crate) fn borrowed(buffer: &'static mut [u8]) -> Self {
+        FixedBuffer { ptr: buffer.as_mut_ptr(), len: buffer.len(), #[cfg(feature = "std")] owned: false }
+    }
+
+    pub(crate) unsafe fn from_raw_parts(ptr: *mut u8, len: usize) -> Self {
+        FixedBuffer { ptr, len, #[cfg(feature = "std")] owned: false }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn owned(buffer: Box<[u8]>) -> Self {
+        let len = buffer.len();
+        FixedBuffer { ptr: Box::into_raw(buffer) as *mut u8, len, owned: true }
+    }
+
+    /// The size of the buffer
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer is empty, leaving no room for anything
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+unsafe impl PageSource for FixedBuffer {
+    fn reserve(&mut self, size: usize) -> Result<*mut u8, i32> {
+        if size > self.len {
+            return Err(0)
+        }
+        Ok(self.ptr)
+    }
+
+    fn commits_on_reserve(&self) -> bool {
+        true
+    }
+
+    unsafe fn commit(&mut self, _ptr: *mut u8, _len: usize) -> Result<(), i32> {
+        Ok(())
+    }
+
+    unsafe fn decommit(&mut self, _ptr: *mut u8, _len: usize) {}
+
+    unsafe fn release(&mut self, _ptr: *mut u8, _size: usize) {
+        #[cfg(feature = "std")]
+        if self.owned {
+            drop(Box::from_raw(core::ptr::slice_from_raw_parts_mut(self.ptr, self.len)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BumpAlloc;
+
+    /// Runs without the `std` feature too, where a `FixedBuffer` is the only `PageSource`
+    #[test]
+    fn fixed_buffer() {
+        let mut buffer = [0u64; 8];
+        let mut alloc = unsafe { BumpAlloc::from_raw_parts(buffer.as_mut_ptr().cast(), 64) };
+        let mut alloc = alloc.to_ref();
+
+        let s1: &mut [u8] = {
+            let mut v1 = alloc.top();
+            v1.extend_from_slice(&[1; 30]);
+            v1.freeze()
+        };
+        let s2: &mut [u32] = {
+            let mut v2 = alloc.top_of();
+            v2.extend_from_slice(&[2, 3]);
+            v2.freeze()
+        };
+        assert_eq!(s1, [1; 30]);
+        assert_eq!(s2, [2, 3]);
+
+        let mut v3 = alloc.top();
+        let err = v3.try_reserve(64).unwrap_err();
+        assert_eq!((err.remaining(), err.errno()), (64 - 40, 0));
+        v3.extend_from_slice(&[4; 24]);
+        assert!(v3.try_extend_from_slice(&[5]).is_err());
+        assert_eq!(v3.freeze(), [4; 24]);
+    }
+}
@@ -0,0 +1,285 @@
+use core::ops::{Deref, DerefMut};
+use crate::{BumpAllocRef, CapacityError, LiquidVecRef};
+
+/// The top vector of a `BumpAlloc` as a growing string, which freezes into a `&mut str`
+///
+/// A thin wrapper around a byte `LiquidVecRef` that only ever appends whole UTF-8 sequences, so the bytes are valid
+/// UTF-8 at every point.
+#[repr(transparent)]
+pub struct LiquidStringRef<'alloc, 'data> {
+    vec: LiquidVecRef<'alloc, 'data, u8>,
+}
+
+impl<'data> BumpAllocRef<'data> {
+    /// Gets the top vector as a string
+    ///
+    /// Panics if the top vector already holds bytes that aren't valid UTF-8, see `LiquidStringRef::from_utf8`.
+    pub fn top_string<'alloc>(&'alloc mut self) -> LiquidStringRef<'alloc, 'data> {
+        match LiquidStringRef::from_utf8(self.top()) {
+            Ok(s) => s,
+            Err(e) => panic!("BumpAllocRef::top_string: the top vector isn't valid UTF-8: {e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'data> BumpAllocRef<'data> {
+    /// Copies an `OsStr` into the arena, keeping its platform encoding
+    ///
+    /// Panics if the top vector isn't empty or the arena is exhausted.
+    pub fn alloc_os_str(&mut self, s: &std::ffi::OsStr) -> &'data std::ffi::OsStr {
+        let mut v = self.empty_top("BumpAllocRef::alloc_os_str");
+        v.extend_from_slice(s.as_encoded_bytes());
+        unsafe { std::ffi::OsStr::from_encoded_bytes_unchecked(v.freeze_shared()) }
+    }
+
+    /// Copies a `Path` into the arena, see `alloc_os_str`
+    #[inline(always)]
+    pub fn alloc_path(&mut self, path: &std::path::Path) -> &'data std::path::Path {
+        std::path::Path::new(self.alloc_os_str(path.as_os_str()))
+    }
+}
+
+impl<'alloc, 'data> LiquidStringRef<'alloc, 'data> {
+    /// Wraps a byte vector as a string, after checking its bytes are valid UTF-8
+    ///
+    /// On error the vector is dropped, so the allocator's `DropPolicy` decides what happens to the bytes.
+    pub fn from_utf8(vec: LiquidVecRef<'alloc, 'data, u8>) -> Result<Self, core::str::Utf8Error> {
+        core::str::from_utf8(&vec)?;
+        Ok(Self { vec })
+    }
+
+    /// Freeze the string, finishing the allocation
+    #[inline(always)]
+    pub fn freeze(self) -> &'data mut str {
+        unsafe { self.vec.freeze_str_unchecked() }
+    }
+
+    /// Unwraps the byte vector, which may then be appended anything
+    #[inline(always)]
+    pub fn into_bytes(self) -> LiquidVecRef<'alloc, 'data, u8> {
+        self.vec
+    }
+
+    /// Appends a character, panicking when the arena is full
+    #[inline(always)]
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Like `push`, but returns an error instead of panicking when the arena is full
+    #[inline(always)]
+    pub fn try_push(&mut self, c: char) -> Result<(), CapacityError> {
+        self.try_push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Appends a string, panicking when the arena is full
+    #[inline(always)]
+    pub fn push_str(&mut self, s: &str) {
+        self.vec.extend_from_slice(s.as_bytes())
+    }
+
+    /// Like `push_str`, but returns an error instead of panicking when the arena is full
+    #[inline(always)]
+    pub fn try_push_str(&mut self, s: &str) -> Result<(), CapacityError> {
+        self.vec.try_extend_from_slice(s.as_bytes())
+    }
+
+    /// Removes the last character and returns it
+    pub fn pop(&mut self) -> Option<char> {
+        let c = self.chars().next_back()?;
+        self.vec.truncate(self.len() - c.len_utf8());
+        Some(c)
+    }
+
+    /// Shortens the string to `new_len` bytes, giving the rest back to the arena
+    ///
+    /// Does nothing if the string is already shorter, panics if `new_len` isn't on a character boundary.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            assert!(self.is_char_boundary(new_len), "truncate: {new_len} is not a char boundary");
+            self.vec.truncate(new_len)
+        }
+    }
+
+    /// Removes the whole string, giving its space back to the arena
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.vec.clear()
+    }
+
+    /// The string built so far
+    #[inline(always)]
+    pub fn as_str(&self) -> &str {
+        unsafe { core::str::from_utf8_unchecked(&self.vec) }
+    }
+
+    /// The string built so far, mutably
+    #[inline(always)]
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe { core::str::from_utf8_unchecked_mut(&mut self.vec) }
+    }
+}
+
+/// Wide strings for Windows APIs, built in a vector from `BumpAllocRef::top_of::<u16>`, which aligns it
+impl<'alloc, 'data> LiquidVecRef<'alloc, 'data, u16> {
+    /// Appends `s` encoded as UTF-16, panicking when the arena is full
+    #[inline(always)]
+    pub fn extend_from_utf16(&mut self, s: &str) {
+        if let Err(e) = self.try_extend_from_utf16(s) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `extend_from_utf16`, but returns an error instead of panicking when the arena is full
+    pub fn try_extend_from_utf16(&mut self, s: &str) -> Result<(), CapacityError> {
+        self.try_reserve(s.encode_utf16().count())?;
+        for unit in s.encode_utf16() {
+            self.push(unit)
+        }
+        Ok(())
+    }
+
+    /// Appends a NUL terminator and freezes, so `as_ptr` on the result is a C wide string (an `LPCWSTR`)
+    ///
+    /// The returned slice includes the terminator.
+    #[inline(always)]
+    pub fn freeze_wide(mut self) -> &'data [u16] {
+        self.push(0);
+        self.freeze_shared()
+    }
+}
+
+impl<'alloc, 'data> Deref for LiquidStringRef<'alloc, 'data> {
+    type Target = str;
+
+    #[inline(always)]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'alloc, 'data> DerefMut for LiquidStringRef<'alloc, 'data> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut str {
+        self.as_mut_str()
+    }
+}
+
+impl<'alloc, 'data> core::fmt::Write for LiquidStringRef<'alloc, 'data> {
+    #[inline(always)]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.try_push_str(s).map_err(|_| core::fmt::Error)
+    }
+
+    #[inline(always)]
+    fn write_char(&mut self, c: char) -> core::fmt::Result {
+        self.try_push(c).map_err(|_| core::fmt::Error)
+    }
+}
+
+impl<'alloc, 'data> core::fmt::Debug for LiquidStringRef<'alloc, 'data> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<'alloc, 'data> core::fmt::Display for LiquidStringRef<'alloc, 'data> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<'alloc, 'data> PartialEq<str> for LiquidStringRef<'alloc, 'data> {
+    #[inline(always)]
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'alloc, 'data, 'a> PartialEq<&'a str> for LiquidStringRef<'alloc, 'data> {
+    #[inline(always)]
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<'alloc, 'data> AsRef<str> for LiquidStringRef<'alloc, 'data> {
+    #[inline(always)]
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<'alloc, 'data> Extend<char> for LiquidStringRef<'alloc, 'data> {
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        for c in iter {
+            self.push(c)
+        }
+    }
+}
+
+impl<'alloc, 'data, 'a> Extend<&'a str> for LiquidStringRef<'alloc, 'data> {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        for s in iter {
+            self.push_str(s)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use core::fmt::Write;
+    use crate::BumpAlloc;
+    use super::LiquidStringRef;
+
+    #[test]
+    fn string() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let mut s1 = alloc.top_string();
+        s1.push_str("SELECT ");
+        s1.extend(["a", ", ", "b"]);
+        write!(s1, " FROM t{}", 1).unwrap();
+        s1.push('é');
+        assert_eq!(s1, "SELECT a, b FROM t1é");
+        assert_eq!(s1.pop(), Some('é'));
+        s1.truncate(11);
+        s1.make_ascii_lowercase();
+        assert_eq!(s1.freeze(), "select a, b");
+
+        let mut s2 = alloc.top_string();
+        s2.extend("ö€".chars());
+        let mut bytes = s2.into_bytes();
+        bytes.push(0xFF);
+        assert!(LiquidStringRef::from_utf8(bytes).is_err());
+        assert_eq!(alloc.data_size(), 11 + 6);
+    }
+
+    #[test]
+    fn wide() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        alloc.top().push(1);
+        alloc.top().freeze();
+        let mut v1 = alloc.top_of::<u16>();
+        v1.extend_from_utf16("C:\\é𝄞");
+        let w1 = v1.freeze_wide();
+        assert_eq!(w1.as_ptr() as usize % align_of::<u16>(), 0);
+        assert_eq!(w1, [b'C' as u16, b':' as u16, b'\\' as u16, 0xE9, 0xD834, 0xDD1E, 0]);
+        assert_eq!(String::from_utf16(&w1[..w1.len() - 1]).unwrap(), "C:\\é𝄞");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn paths() {
+        use std::path::Path;
+
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        let dir = alloc.alloc_path(Path::new("/tmp/scan"));
+        let name = alloc.alloc_os_str("file.txt".as_ref());
+        assert_eq!(dir.join(name), Path::new("/tmp/scan/file.txt"));
+        assert_eq!(alloc.data_size(), 17);
+    }
+}
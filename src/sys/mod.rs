@@ -0,0 +1,184 @@
+//! The OS layer: reserving, committing and releasing the address space behind a `BumpAlloc`
+
+use crate::PageSource;
+
+#[cfg(all(unix, not(feature = "rustix")))]
+mod unix;
+#[cfg(all(unix, not(feature = "rustix")))]
+pub(crate) use unix::*;
+
+#[cfg(all(unix, feature = "rustix"))]
+mod unix_rustix;
+#[cfg(all(unix, feature = "rustix"))]
+pub(crate) use unix_rustix::*;
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+pub(crate) use windows::*;
+
+#[cfg(target_family = "wasm")]
+mod wasm;
+#[cfg(target_family = "wasm")]
+pub(crate) use wasm::*;
+
+/// The OS error code of the last failed call on this thread
+#[cfg(any(windows, all(unix, not(feature = "rustix"))))]
+pub(crate) fn last_error() -> i32 {
+    std::io::Error::last_os_error().raw_os_error().unwrap_or(0)
+}
+
+/// The default source, anonymous memory straight from the OS (`mmap` on unix, `VirtualAlloc` on Windows)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OsPages;
+
+unsafe impl PageSource for OsPages {
+    fn reserve(&mut self, size: usize) -> Result<*mut u8, i32> {
+        unsafe { reserve(size) }
+    }
+
+    fn commits_on_reserve(&self) -> bool {
+        COMMIT_ON_RESERVE
+    }
+
+    fn zeroed(&self) -> bool {
+        true
+    }
+
+    unsafe fn commit(&mut self, ptr: *mut u8, len: usize) -> Result<(), i32> {
+        commit(ptr, len)
+    }
+
+    unsafe fn decommit(&mut self, ptr: *mut u8, len: usize) {
+        decommit(ptr, len)
+    }
+
+    unsafe fn release(&mut self, ptr: *mut u8, size: usize) {
+        release(ptr, size)
+    }
+
+    unsafe fn grow(&mut self, ptr: *mut u8, size: usize, additional: usize) -> Result<(), i32> {
+        grow(ptr, size, additional)
+    }
+
+    unsafe fn will_need(&mut self, ptr: *mut u8, len: usize) {
+        will_need(ptr, len)
+    }
+
+    unsafe fn resident(&self, ptr: *mut u8, len: usize) -> Option<usize> {
+        resident(ptr, len)
+    }
+}
+
+/// Like `OsPages`, with one more page at the end of the reservation that faults when touched
+///
+/// Writes that run off the end of the arena then crash right away, instead of landing in whatever is mapped after it.
+/// On Windows the guard page is simply never committed, wasm has no way to protect memory.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GuardedPages;
+
+unsafe impl PageSource for GuardedPages {
+    fn reserve(&mut self, size: usize) -> Result<*mut u8, i32> {
+        unsafe {
+            let base = reserve(size + page_size())?;
+            protect(base.add(size), page_size(), false);
+            Ok(base)
+        }
+    }
+
+    fn commits_on_reserve(&self) -> bool {
+        COMMIT_ON_RESERVE
+    }
+
+    fn zeroed(&self) -> bool {
+        true
+    }
+
+    unsafe fn commit(&mut self, ptr: *mut u8, len: usize) -> Result<(), i32> {
+        commit(ptr, len)
+    }
+
+    unsafe fn decommit(&mut self, ptr: *mut u8, len: usize) {
+        decommit(ptr, len)
+    }
+
+    unsafe fn release(&mut self, ptr: *mut u8, size: usize) {
+        release(ptr, size + page_size())
+    }
+
+    /// Grows past the guard page, which then moves to the new end
+    unsafe fn grow(&mut self, ptr: *mut u8, size: usize, additional: usize) -> Result<(), i32> {
+        grow(ptr, size + page_size(), additional)?;
+        protect(ptr.add(size + additional), page_size(), false);
+        protect(ptr.add(size), page_size(), true);
+        Ok(())
+    }
+
+    unsafe fn will_need(&mut self, ptr: *mut u8, len: usize) {
+        will_need(ptr, len)
+    }
+
+    unsafe fn resident(&self, ptr: *mut u8, len: usize) -> Option<usize> {
+        resident(ptr, len)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserve_write_release() {
+        unsafe {
+            let base = reserve(1 << 32).unwrap();
+            *base = 1;
+            *base.add((1 << 32) - 1) = 2;
+            will_need(base.add(1), 1 << 20);
+            assert_eq!(*base.add(page_size()), 0);
+            release(base, 1 << 32);
+        }
+    }
+
+    #[test]
+    fn guard_page() {
+        unsafe {
+            let base = GuardedPages.reserve(1 << 20).unwrap();
+            *base.add((1 << 20) - 1) = 1;
+            let child = libc::fork();
+            if child == 0 {
+                base.add(1 << 20).write_volatile(1);
+                libc::_exit(0);
+            }
+            let mut status = 0;
+            libc::waitpid(child, &mut status, 0);
+            assert!(libc::WIFSIGNALED(status));
+            GuardedPages.release(base, 1 << 20);
+        }
+    }
+
+    #[test]
+    fn grow_in_place() {
+        unsafe {
+            let base = reserve(1 << 21).unwrap();
+            release(base.add(1 << 20), 1 << 20);
+            grow(base, 1 << 20, 1 << 20).unwrap();
+            *base.add((1 << 21) - 1) = 1;
+            release(base, 1 << 21);
+        }
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn reserve_beyond_commit_limit() {
+        unsafe {
+            let base = reserve(1 << 40).unwrap();
+            release(base, 1 << 40);
+        }
+    }
+
+    #[test]
+    #[cfg(all(target_vendor = "apple", target_arch = "aarch64"))]
+    fn apple_silicon_pages() {
+        assert_eq!(page_size(), 1 << 14);
+    }
+}
@@ -0,0 +1,119 @@
+use std::ptr::null_mut;
+use libc::*;
+
+pub(crate) const EINVAL: i32 = libc::EINVAL;
+
+/// A 4GiB reservation is only address space, as long as there's enough of it
+pub(crate) const DEFAULT_BITS: u8 = if cfg!(target_pointer_width = "64") { 32 } else { 28 };
+
+/// Anonymous mappings are backed on first touch, so the whole reservation can be written right away
+pub(crate) const COMMIT_ON_RESERVE: bool = true;
+
+/// Linux charges private writable mappings against the commit limit up front unless asked not to,
+/// and the heuristic overcommit mode refuses reservations larger than RAM + swap
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const MAP_FLAGS: c_int = MAP_PRIVATE | MAP_ANONYMOUS | MAP_NORESERVE;
+
+/// Darwin and the BSDs only charge pages once they're touched; `MAP_NORESERVE` is ignored or missing there
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const MAP_FLAGS: c_int = MAP_PRIVATE | MAP_ANON;
+
+/// Reserves `size` bytes of zeroed, readable and writable address space
+pub(crate) unsafe fn reserve(size: usize) -> Result<*mut u8, i32> {
+    let res = mmap(null_mut(), size, PROT_READ | PROT_WRITE, MAP_FLAGS, -1, 0);
+    if res == MAP_FAILED {
+        return Err(super::last_error())
+    }
+    if res.is_null() {
+        munmap(res, size);
+        return Err(0)
+    }
+    Ok(res as *mut u8)
+}
+
+/// Maps the `additional` bytes right after the reservation `[ptr, ptr + size)`, if nothing else is mapped there
+///
+/// Linux refuses with `MAP_FIXED_NOREPLACE`, elsewhere (and on kernels before 4.17) the address is only a hint,
+/// and the mapping is undone if the kernel put it somewhere else.
+pub(crate) unsafe fn grow(ptr: *mut u8, size: usize, additional: usize) -> Result<(), i32> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let flags = MAP_FLAGS | MAP_FIXED_NOREPLACE;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let flags = MAP_FLAGS;
+    let end = ptr.add(size) as *mut c_void;
+    let res = mmap(end, additional, PROT_READ | PROT_WRITE, flags, -1, 0);
+    if res == MAP_FAILED {
+        return Err(super::last_error())
+    }
+    if res != end {
+        munmap(res, additional);
+        return Err(EEXIST)
+    }
+    Ok(())
+}
+
+/// Nothing to do, see `COMMIT_ON_RESERVE`
+pub(crate) unsafe fn commit(_ptr: *mut u8, _len: usize) -> Result<(), i32> {
+    Ok(())
+}
+
+/// The size of a VM page, 16KiB on Apple silicon and 4KiB on most other systems
+pub(crate) fn page_size() -> usize {
+    unsafe { sysconf(_SC_PAGESIZE) as usize }
+}
+
+/// Hints that `[ptr, ptr + len)` is going to be written soon
+///
+/// Linux and FreeBSD prefault anonymous memory for `MADV_WILLNEED`, Darwin accepts the advice but ignores it.
+/// All of them reject ranges that don't start on a page boundary.
+pub(crate) unsafe fn will_need(ptr: *mut u8, len: usize) {
+    let start = ptr as usize & !(page_size() - 1);
+    madvise(start as _, len + (ptr as usize - start), MADV_WILLNEED);
+}
+
+/// Counts the resident pages of `[ptr, ptr + len)` with `mincore`
+pub(crate) unsafe fn resident(ptr: *mut u8, len: usize) -> Option<usize> {
+    let page = page_size();
+    let mut pages = vec![0u8; len.div_ceil(page)];
+    if mincore(ptr as _, len, pages.as_mut_ptr() as _) != 0 {
+        return None;
+    }
+    Some(pages.iter().filter(|&&p| p & 1 != 0).count() * page)
+}
+
+/// Drops the pages backing `[ptr, ptr + len)`, which read as zero when touched again
+///
+/// Linux does exactly that for `MADV_DONTNEED` on private anonymous memory; elsewhere the advice may keep the
+/// contents, so the range is replaced by a fresh mapping instead.
+pub(crate) unsafe fn decommit(ptr: *mut u8, len: usize) {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    madvise(ptr as _, len, MADV_DONTNEED);
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    mmap(ptr as _, len, PROT_READ | PROT_WRITE, MAP_FLAGS | MAP_FIXED, -1, 0);
+}
+
+/// Makes `[ptr, ptr + len)` fault when touched, or accessible again
+pub(crate) unsafe fn protect(ptr: *mut u8, len: usize, accessible: bool) {
+    mprotect(ptr as _, len, if accessible { PROT_READ | PROT_WRITE } else { PROT_NONE });
+}
+
+/// Reads up to `len` bytes from `fd` into `ptr` with one `read` call
+pub(crate) unsafe fn read_fd(fd: c_int, ptr: *mut u8, len: usize) -> Result<usize, i32> {
+    match libc::read(fd, ptr as _, len) {
+        n @ 0.. => Ok(n as usize),
+        _ => Err(super::last_error()),
+    }
+}
+
+/// Writes up to `len` bytes from `ptr` to `fd` with one `write` call
+pub(crate) unsafe fn write_fd(fd: c_int, ptr: *const u8, len: usize) -> Result<usize, i32> {
+    match libc::write(fd, ptr as _, len) {
+        n @ 0.. => Ok(n as usize),
+        _ => Err(super::last_error()),
+    }
+}
+
+/// Gives a reservation back to the OS
+pub(crate) unsafe fn release(ptr: *mut u8, size: usize) {
+    munmap(ptr as _, size);
+}
@@ -0,0 +1,100 @@
+use std::ptr::null_mut;
+use rustix::io::Errno;
+use rustix::mm::*;
+
+pub(crate) const EINVAL: i32 = Errno::INVAL.raw_os_error();
+
+/// A 4GiB reservation is only address space, as long as there's enough of it
+pub(crate) const DEFAULT_BITS: u8 = if cfg!(target_pointer_width = "64") { 32 } else { 28 };
+
+/// Anonymous mappings are backed on first touch, so the whole reservation can be written right away
+pub(crate) const COMMIT_ON_RESERVE: bool = true;
+
+/// Linux charges private writable mappings against the commit limit up front unless asked not to,
+/// and the heuristic overcommit mode refuses reservations larger than RAM + swap
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const MAP_FLAGS: MapFlags = MapFlags::PRIVATE.union(MapFlags::NORESERVE);
+
+/// Darwin and the BSDs only charge pages once they're touched
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const MAP_FLAGS: MapFlags = MapFlags::PRIVATE;
+
+/// Reserves `size` bytes of zeroed, readable and writable address space
+pub(crate) unsafe fn reserve(size: usize) -> Result<*mut u8, i32> {
+    match mmap_anonymous(null_mut(), size, ProtFlags::READ | ProtFlags::WRITE, MAP_FLAGS) {
+        Ok(res) if res.is_null() => {
+            let _ = munmap(res, size);
+            Err(0)
+        }
+        Ok(res) => Ok(res as *mut u8),
+        Err(errno) => Err(errno.raw_os_error()),
+    }
+}
+
+/// Maps the `additional` bytes right after the reservation `[ptr, ptr + size)`, see the libc backend
+pub(crate) unsafe fn grow(ptr: *mut u8, size: usize, additional: usize) -> Result<(), i32> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let flags = MAP_FLAGS | MapFlags::FIXED_NOREPLACE;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let flags = MAP_FLAGS;
+    let end = ptr.add(size) as *mut _;
+    let res = mmap_anonymous(end, additional, ProtFlags::READ | ProtFlags::WRITE, flags).map_err(Errno::raw_os_error)?;
+    if res != end {
+        let _ = munmap(res, additional);
+        return Err(Errno::EXIST.raw_os_error())
+    }
+    Ok(())
+}
+
+/// Nothing to do, see `COMMIT_ON_RESERVE`
+pub(crate) unsafe fn commit(_ptr: *mut u8, _len: usize) -> Result<(), i32> {
+    Ok(())
+}
+
+/// The size of a VM page, 16KiB on Apple silicon and 4KiB on most other systems
+pub(crate) fn page_size() -> usize {
+    rustix::param::page_size()
+}
+
+/// Hints that `[ptr, ptr + len)` is going to be written soon, see the libc backend
+pub(crate) unsafe fn will_need(ptr: *mut u8, len: usize) {
+    let start = ptr as usize & !(page_size() - 1);
+    let _ = madvise(start as _, len + (ptr as usize - start), Advice::WillNeed);
+}
+
+/// rustix has no `mincore`, so residency is unknown
+pub(crate) unsafe fn resident(_ptr: *mut u8, _len: usize) -> Option<usize> {
+    None
+}
+
+/// Drops the pages backing `[ptr, ptr + len)`, which read as zero when touched again, see the libc backend
+pub(crate) unsafe fn decommit(ptr: *mut u8, len: usize) {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let _ = madvise(ptr as _, len, Advice::LinuxDontNeed);
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let _ = mmap_anonymous(ptr as _, len, ProtFlags::READ | ProtFlags::WRITE, MAP_FLAGS | MapFlags::FIXED);
+}
+
+/// Makes `[ptr, ptr + len)` fault when touched, or accessible again
+pub(crate) unsafe fn protect(ptr: *mut u8, len: usize, accessible: bool) {
+    let flags = if accessible { MprotectFlags::READ | MprotectFlags::WRITE } else { MprotectFlags::empty() };
+    let _ = mprotect(ptr as _, len, flags);
+}
+
+/// Reads up to `len` bytes from `fd` into `ptr` with one `read` call
+pub(crate) unsafe fn read_fd(fd: i32, ptr: *mut u8, len: usize) -> Result<usize, i32> {
+    let fd = std::os::fd::BorrowedFd::borrow_raw(fd);
+    let buf = std::slice::from_raw_parts_mut(ptr as *mut std::mem::MaybeUninit<u8>, len);
+    rustix::io::read(fd, buf).map(|(read, _)| read.len()).map_err(Errno::raw_os_error)
+}
+
+/// Writes up to `len` bytes from `ptr` to `fd` with one `write` call
+pub(crate) unsafe fn write_fd(fd: i32, ptr: *const u8, len: usize) -> Result<usize, i32> {
+    let fd = std::os::fd::BorrowedFd::borrow_raw(fd);
+    rustix::io::write(fd, std::slice::from_raw_parts(ptr, len)).map_err(Errno::raw_os_error)
+}
+
+/// Gives a reservation back to the OS
+pub(crate) unsafe fn release(ptr: *mut u8, size: usize) {
+    let _ = munmap(ptr as _, size);
+}
@@ -0,0 +1,69 @@
+use core::arch::wasm32;
+
+/// The unit linear memory grows by
+const WASM_PAGE: usize = 1 << 16;
+
+/// WASI's `EINVAL`, there are no OS error codes on `wasm32-unknown-unknown`
+pub(crate) const EINVAL: i32 = 28;
+
+/// WASI's `ENOMEM`
+const ENOMEM: i32 = 48;
+
+/// Grown linear memory is zeroed and usable right away
+pub(crate) const COMMIT_ON_RESERVE: bool = true;
+
+/// Everything reserved is committed, so keep the default small
+pub(crate) const DEFAULT_BITS: u8 = 24;
+
+/// Grows linear memory by enough pages for `size` bytes, returning the start of the new pages
+///
+/// There's no mmap to pick a free range, so the reservation is always appended at the end of linear memory.
+pub(crate) unsafe fn reserve(size: usize) -> Result<*mut u8, i32> {
+    let old = wasm32::memory_grow::<0>(size.div_ceil(WASM_PAGE));
+    if old == usize::MAX {
+        return Err(ENOMEM)
+    }
+    Ok((old*WASM_PAGE) as *mut u8)
+}
+
+/// Grows linear memory further, which only extends the reservation if it's still at the end of linear memory
+pub(crate) unsafe fn grow(ptr: *mut u8, size: usize, additional: usize) -> Result<(), i32> {
+    let end = (ptr as usize + size).next_multiple_of(WASM_PAGE);
+    if end != wasm32::memory_size::<0>()*WASM_PAGE {
+        return Err(ENOMEM)
+    }
+    let pages = (ptr as usize + size + additional).next_multiple_of(WASM_PAGE) - end;
+    if wasm32::memory_grow::<0>(pages/WASM_PAGE) == usize::MAX {
+        return Err(ENOMEM)
+    }
+    Ok(())
+}
+
+/// Nothing to do, see `COMMIT_ON_RESERVE`
+pub(crate) unsafe fn commit(_ptr: *mut u8, _len: usize) -> Result<(), i32> {
+    Ok(())
+}
+
+/// Linear memory can't give pages back, so this only restores the zeroes
+pub(crate) unsafe fn decommit(ptr: *mut u8, len: usize) {
+    ptr.write_bytes(0, len);
+}
+
+/// The unit memory is reserved in
+pub(crate) fn page_size() -> usize {
+    WASM_PAGE
+}
+
+/// Linear memory is accessible all the way, there's no protecting it
+pub(crate) unsafe fn protect(_ptr: *mut u8, _len: usize, _accessible: bool) {}
+
+/// Linear memory can't shrink, the pages stay part of the instance
+pub(crate) unsafe fn release(_ptr: *mut u8, _size: usize) {}
+
+/// There's no paging to prepare
+pub(crate) unsafe fn will_need(_ptr: *mut u8, _len: usize) {}
+
+/// Linear memory is all resident
+pub(crate) unsafe fn resident(_ptr: *mut u8, len: usize) -> Option<usize> {
+    Some(len)
+}
@@ -0,0 +1,74 @@
+use std::ffi::c_void;
+use std::ptr::null_mut;
+
+const MEM_COMMIT: u32 = 0x1000;
+const MEM_RESERVE: u32 = 0x2000;
+const MEM_DECOMMIT: u32 = 0x4000;
+const MEM_RELEASE: u32 = 0x8000;
+const PAGE_READWRITE: u32 = 0x04;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn VirtualAlloc(address: *mut c_void, size: usize, allocation_type: u32, protect: u32) -> *mut c_void;
+    fn VirtualFree(address: *mut c_void, size: usize, free_type: u32) -> i32;
+}
+
+/// `ERROR_INVALID_PARAMETER`
+pub(crate) const EINVAL: i32 = 87;
+
+/// A 4GiB reservation is only address space, as long as there's enough of it
+pub(crate) const DEFAULT_BITS: u8 = if cfg!(target_pointer_width = "64") { 32 } else { 28 };
+
+/// Reserved pages can't be touched before they're committed, and committing counts against the system commit limit,
+/// so the arena commits as the top vector grows
+pub(crate) const COMMIT_ON_RESERVE: bool = false;
+
+/// Reserves `size` bytes of address space, without committing any of it
+pub(crate) unsafe fn reserve(size: usize) -> Result<*mut u8, i32> {
+    let res = VirtualAlloc(null_mut(), size, MEM_RESERVE, PAGE_READWRITE);
+    if res.is_null() {
+        return Err(super::last_error())
+    }
+    Ok(res as *mut u8)
+}
+
+/// `VirtualFree` releases one reservation at a time, so an arena can't be made of several of them
+pub(crate) unsafe fn grow(_ptr: *mut u8, _size: usize, _additional: usize) -> Result<(), i32> {
+    /// `ERROR_NOT_SUPPORTED`
+    const NOT_SUPPORTED: i32 = 50;
+    Err(NOT_SUPPORTED)
+}
+
+/// Commits the reserved pages covering `[ptr, ptr + len)`, which are then zeroed, readable and writable
+pub(crate) unsafe fn commit(ptr: *mut u8, len: usize) -> Result<(), i32> {
+    if VirtualAlloc(ptr as _, len, MEM_COMMIT, PAGE_READWRITE).is_null() {
+        return Err(super::last_error())
+    }
+    Ok(())
+}
+
+/// There's no cheap equivalent of `MADV_WILLNEED` for reserved memory, committing is done by `commit`
+pub(crate) unsafe fn will_need(_ptr: *mut u8, _len: usize) {}
+
+/// Residency would take `QueryWorkingSetEx` from psapi, which isn't linked
+pub(crate) unsafe fn resident(_ptr: *mut u8, _len: usize) -> Option<usize> {
+    None
+}
+
+/// Decommits the pages covering `[ptr, ptr + len)`, which stay reserved
+pub(crate) unsafe fn decommit(ptr: *mut u8, len: usize) {
+    VirtualFree(ptr as _, len, MEM_DECOMMIT);
+}
+
+/// The size of a VM page
+pub(crate) fn page_size() -> usize {
+    1 << 12
+}
+
+/// Pages the arena never commits fault anyway, so there's nothing to protect
+pub(crate) unsafe fn protect(_ptr: *mut u8, _len: usize, _accessible: bool) {}
+
+/// Gives a reservation back to the OS
+pub(crate) unsafe fn release(ptr: *mut u8, _size: usize) {
+    VirtualFree(ptr as _, 0, MEM_RELEASE);
+}
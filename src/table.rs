@@ -0,0 +1,186 @@
+use crate::{BumpAllocRef, CapacityError, LiquidVecRef};
+
+/// Builds a table of variable-length byte entries in the top vector, see `BumpAllocRef::top_table`
+///
+/// Each entry is preceded by its length while the table is being built. `freeze_table` strips those out, leaving the
+/// entries back to back followed by an index of where each one ends, which is the layout of most symbol tables and
+/// column chunks.
+pub struct TableBuilder<'alloc, 'data> {
+    vec: LiquidVecRef<'alloc, 'data, u8>,
+    data_len: usize,
+    count: usize,
+}
+
+/// The entries of a frozen `TableBuilder`, along with their index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrozenTable<'data> {
+    data: &'data [u8],
+    ends: &'data [u32],
+}
+
+/// Why a `TableBuilder` can't take another entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableError {
+    /// The arena is full
+    Capacity(CapacityError),
+    /// The entries would add up to more than `u32::MAX` bytes, which the index can't point past
+    OffsetOverflow,
+}
+
+impl From<CapacityError> for TableError {
+    fn from(e: CapacityError) -> Self {
+        TableError::Capacity(e)
+    }
+}
+
+impl core::fmt::Display for TableError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TableError::Capacity(e) => e.fmt(f),
+            TableError::OffsetOverflow => write!(f, "TableBuilder: the entries are over u32::MAX bytes"),
+        }
+    }
+}
+
+impl core::error::Error for TableError {}
+
+const HEADER: usize = size_of::<u32>();
+
+impl<'data> BumpAllocRef<'data> {
+    /// Gets the top vector as a table builder
+    ///
+    /// Panics if the top vector isn't empty.
+    pub fn top_table<'alloc>(&'alloc mut self) -> TableBuilder<'alloc, 'data> {
+        let vec = self.empty_top::<u8>("BumpAllocRef::top_table");
+        vec.alloc.align_top(align_of::<u32>());
+        TableBuilder { vec, data_len: 0, count: 0 }
+    }
+}
+
+impl<'alloc, 'data> TableBuilder<'alloc, 'data> {
+    /// Appends an entry, panicking when the arena is full
+    ///
+    /// Also panics if the entries would add up to more than `u32::MAX` bytes, as the index holds `u32` offsets.
+    #[inline(always)]
+    pub fn push(&mut self, entry: &[u8]) {
+        if let Err(e) = self.try_push(entry) {
+            panic!("{e}")
+        }
+    }
+
+    /// Like `push`, but returns an error instead of panicking when the arena is full or the entries get too big for
+    /// the index, appending nothing
+    pub fn try_push(&mut self, entry: &[u8]) -> Result<(), TableError> {
+        let len = u32::try_from(entry.len()).ok().filter(|len| self.data_len + *len as usize <= u32::MAX as usize);
+        let len = len.ok_or(TableError::OffsetOverflow)?;
+        self.vec.try_reserve(HEADER + entry.len())?;
+        self.vec.extend_from_slice(&len.to_ne_bytes());
+        self.vec.extend_from_slice(entry);
+        self.data_len += entry.len();
+        self.count += 1;
+        Ok(())
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Whether there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Freeze the table, moving the entries together and writing the index after them
+    ///
+    /// The index is put together past the end of the entries first, so this needs 4 more bytes per entry for a
+    /// moment, which the length headers give back. Panics when the arena is full.
+    pub fn freeze_table(mut self) -> FrozenTable<'data> {
+        let (count, data_len) = (self.count, self.data_len);
+        let index = data_len.next_multiple_of(HEADER);
+        let scratch = self.vec.len();
+        self.vec.extend_zeroed(count * HEADER);
+        let bytes: &mut [u8] = &mut self.vec;
+        let (mut read, mut write) = (0, 0);
+        for i in 0..count {
+            let len = u32::from_ne_bytes(bytes[read..read + HEADER].try_into().unwrap()) as usize;
+            bytes.copy_within(read + HEADER..read + HEADER + len, write);
+            read += HEADER + len;
+            write += len;
+            bytes[scratch + i * HEADER..][..HEADER].copy_from_slice(&(write as u32).to_ne_bytes());
+        }
+        bytes[data_len..index].fill(0);
+        bytes.copy_within(scratch..scratch + count * HEADER, index);
+        self.vec.truncate(index + count * HEADER);
+        let (data, ends) = self.vec.freeze_shared().split_at(index);
+        FrozenTable { data: &data[..data_len], ends: unsafe { core::slice::from_raw_parts(ends.as_ptr().cast(), count) } }
+    }
+}
+
+impl<'data> FrozenTable<'data> {
+    /// The entry at `index`
+    pub fn get(&self, index: usize) -> Option<&'data [u8]> {
+        let end = *self.ends.get(index)? as usize;
+        let start = index.checked_sub(1).map_or(0, |i| self.ends[i] as usize);
+        Some(&self.data[start..end])
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Whether there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// The entries in the order they were pushed
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &'data [u8]> + ExactSizeIterator + '_ {
+        (0..self.len()).map(|i| self.get(i).unwrap())
+    }
+
+    /// All the entries back to back
+    pub fn data(&self) -> &'data [u8] {
+        self.data
+    }
+
+    /// The offset each entry ends at in `data`, which directly follows it in the arena, after padding to 4 bytes
+    pub fn ends(&self) -> &'data [u32] {
+        self.ends
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::BumpAlloc;
+    use super::TableError;
+
+    #[test]
+    fn table() {
+        let mut alloc = BumpAlloc::new();
+        let mut alloc = alloc.to_ref();
+        alloc.alloc(1u8);
+        let mut builder = alloc.top_table();
+        for name in ["main", "", "alloc_slice_copy", "top"] {
+            builder.push(name.as_bytes());
+        }
+        assert_eq!(builder.len(), 4);
+        let table = builder.freeze_table();
+        assert_eq!(table.data(), b"mainalloc_slice_copytop");
+        assert_eq!(table.ends(), [4, 4, 20, 23]);
+        assert_eq!((table.get(1), table.get(2), table.get(4)), (Some(&b""[..]), Some(&b"alloc_slice_copy"[..]), None));
+        assert!(table.iter().rev().map(|e| e.len()).eq([3, 16, 0, 4]));
+        assert_eq!(table.data().as_ptr() as usize % 4, 0);
+        assert_eq!(unsafe { table.data().as_ptr().add(24) }, table.ends().as_ptr().cast());
+        assert_eq!(alloc.data_size(), 4 + 24 + 16);
+        assert!(alloc.top_table().freeze_table().is_empty());
+
+        let mut builder = alloc.top_table();
+        builder.push(b"ab");
+        builder.data_len = u32::MAX as usize - 1;
+        assert_eq!(builder.try_push(b"c"), Ok(()));
+        assert_eq!(builder.try_push(b"d"), Err(TableError::OffsetOverflow));
+        assert_eq!(builder.len(), 2);
+    }
+}